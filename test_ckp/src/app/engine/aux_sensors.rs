@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app::engine::{
+    calibration::{apply_calibration, Calibration},
+    filters::{FilterConfig, SensorFilter},
+};
+
+// entradas del multiplexor de ADC2 (mux_a/b/c)
+pub const MUX_CHANNELS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum AuxSensorType {
+    // canal sin usar, no se lee
+    None,
+    // kPa
+    OilPressure,
+    FuelPressure,
+    // °C
+    OilTemp,
+    // lambda del segundo banco
+    SecondLambda,
+    // solo queda en `value`, en la unidad de la calibracion
+    Generic,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct AuxChannelConfig {
+    pub sensor: AuxSensorType,
+    pub calibration: Calibration,
+    pub filter: FilterConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct AuxSensorsConfig {
+    pub enabled: bool,
+    // uS entre cambiar el mux y convertir, menos de 100uS se espera con el CPU (ver tasks/sensors.rs)
+    pub settle_time: u32,
+    // mS entre barridos
+    pub interval: u32,
+    pub channels: [AuxChannelConfig; MUX_CHANNELS],
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct AuxSensorValues {
+    pub oil_pressure: f32,
+    pub fuel_pressure: f32,
+    pub oil_temp: f32,
+    pub lambda_2: f32,
+
+    // por canal del mux: mV sin filtrar y valor calibrado
+    pub raw: [f32; MUX_CHANNELS],
+    pub value: [f32; MUX_CHANNELS],
+
+    // private:
+    filters: [SensorFilter; MUX_CHANNELS],
+}
+
+impl AuxSensorValues {
+    pub fn new() -> AuxSensorValues {
+        AuxSensorValues {
+            oil_pressure: 0.0,
+            fuel_pressure: 0.0,
+            oil_temp: 0.0,
+            lambda_2: 1.0,
+            raw: [0.0; MUX_CHANNELS],
+            value: [0.0; MUX_CHANNELS],
+            filters: [SensorFilter::new(); MUX_CHANNELS],
+        }
+    }
+
+    /**
+     * @brief `millivolts` leidos en el canal `channel` del mux
+     */
+    pub fn update(&mut self, channel: usize, millivolts: u16, cfg: &AuxChannelConfig) {
+        let raw = millivolts as f32;
+        let filtered = self.filters[channel].apply(raw, &cfg.filter);
+        let value = apply_calibration(filtered, &cfg.calibration);
+
+        self.raw[channel] = raw;
+        self.value[channel] = value;
+
+        match cfg.sensor {
+            AuxSensorType::OilPressure => self.oil_pressure = value,
+            AuxSensorType::FuelPressure => self.fuel_pressure = value,
+            AuxSensorType::OilTemp => self.oil_temp = value,
+            AuxSensorType::SecondLambda => self.lambda_2 = value,
+            AuxSensorType::Generic | AuxSensorType::None => {}
+        }
+    }
+}
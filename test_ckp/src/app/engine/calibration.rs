@@ -0,0 +1,19 @@
+pub use open_efi::math::calibration::{apply_calibration, linear_between, Calibration, ADC_FULL_SCALE_MV};
+
+use crate::app::engine::sensor_cfg::MapPreset;
+
+/**
+ * @brief kPa con la salida en 0V y en 5V (fondo de escala del ADC) de MAP comunes
+ */
+pub fn get_map_preset(preset: MapPreset) -> Calibration {
+    let (min_kpa, max_kpa) = match preset {
+        MapPreset::Gm1Bar => (10.0, 105.0),
+        MapPreset::Gm2Bar => (8.8, 208.0),
+        MapPreset::Gm3Bar => (1.1, 315.5),
+        MapPreset::Mpx4115 => (10.6, 121.7),
+        MapPreset::Mpx4250 => (10.0, 260.0),
+    };
+
+    linear_between(0.0, min_kpa, ADC_FULL_SCALE_MV, max_kpa)
+}
+
@@ -13,6 +13,8 @@ pub struct VRStatus {
     pub tooth_one_time: u32,
     pub tooth_one_minus_one_time: u32,
     pub has_sync: bool,
+    // sabe en que vuelta del ciclo de 720° esta (CMP), sin decoder de CMP queda siempre en false
+    pub has_phase: bool,
     // por ahora es el 'half-sinc' de speeduino
     pub sync_loss_counter: u128,
     pub start_revolution: u128,
@@ -35,6 +37,7 @@ impl VRStatus {
             tooth_one_time: 0,
             tooth_one_minus_one_time: 0,
             has_sync: false,
+            has_phase: false,
             sync_loss_counter: 0,
             start_revolution: 0,
             last_rpm: 0,
@@ -54,6 +57,7 @@ impl VRStatus {
         self.tooth_one_time = 0;
         self.tooth_one_minus_one_time = 0;
         self.has_sync = false;
+        self.has_phase = false;
         self.sync_loss_counter = 0;
         self.start_revolution = 0;
         self.last_rpm = 0;
@@ -0,0 +1 @@
+pub use open_efi::math::diagnostics::{DiagnosticCode, Diagnostics};
@@ -1,6 +1,12 @@
-use crate::app::memory::tables::PlotData;
+use postcard::experimental::max_size::MaxSize;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+use crate::app::{
+    ignition::get_coil_count,
+    injection::scheduler::{effective_mode, get_active_channels, get_trim_outputs, is_output_trim_uniform},
+    memory::tables::PlotData,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
 pub struct VRSensor {
     pub trigger_tooth_angle: f32,
     pub tooth_count: u32,
@@ -11,47 +17,309 @@ pub struct VRSensor {
     pub max_stall_time: u32,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+pub const MAX_CYLINDERS: usize = 8;
+
+// correcciones por cilindro, en orden de encendido; los cilindros que comparten salida
+// (semi-secuencial, batch, chispa perdida) usan el promedio de los suyos
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct CylinderTrimConfig {
+    // % de combustible (100 = sin correccion)
+    pub fuel: [f32; MAX_CYLINDERS],
+    // grados sumados al avance
+    pub spark: [f32; MAX_CYLINDERS],
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
 pub struct Engine {
     pub cylinder_count: u8,
     pub displacement: u32,
     pub max_rpm: u32,
     pub ckp: VRSensor,
+    pub cylinder_trim: CylinderTrimConfig,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
 pub struct InjectorConfig {
     pub flow_cc_min: f32,
     pub injector_count: u8,
     pub fuel_pressure: f32,
+    // presion a la que se midio flow_cc_min
+    pub flow_ref_pressure: f32,
     pub fuel_density: f32,
+    // uS, tiempo muerto a tension nominal = on_time - off_time
     pub on_time: f32,
     pub off_time: f32,
+    // %, maximo del tiempo disponible por inyeccion
+    pub max_duty_cycle: f32,
+    // uS, pulsos (sin tiempo muerto) mas cortos no se inyectan
+    pub min_pulse_width: f32,
+    // x: uS de pulso sin tiempo muerto, y: uS a sumar (alinealidad de pulsos cortos),
+    // el ultimo punto tendria que ir en 0
+    pub small_pulse_correction: Option<PlotData>,
 
+    // x: mV de bateria, y: tiempo muerto en uS (si no esta cargada Tables.vbat_correction)
     pub battery_correction: Option<PlotData>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum InjectionMode {
+    // todos los inyectores juntos, dos veces por ciclo
+    Batch,
+    // de a pares (1-4 / 2-3), una vez por vuelta
+    SemiSequential,
+    // uno por cilindro, una vez por ciclo; necesita fase del CMP, sin ella corre en semi-secuencial
+    Sequential,
+}
+
+// eje de carga de la tabla de VE en speed-density
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum VeLoadAxis {
+    Map,
+    Tps,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct AlphaNConfig {
+    // hibrido, multiplica por MAP / baro
+    pub multiply_map: bool,
+}
+
+// en speed-density la densidad siempre sale de la IAT por gas ideal, Off e IdealGas dan lo mismo
+// y Curve agrega el ajuste de iat_curve; en alpha-N es la unica correccion por IAT
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum IatCorrection {
+    Off,
+    // densidad por gas ideal respecto a 20°C
+    IdealGas,
+    // curva iat_curve
+    Curve,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum BaroSource {
+    // fixed_baro
+    Fixed,
+    // MAP con el motor parado (al dar contacto)
+    Map,
+    // sensor de baro propio (baro_cs), se lee siempre
+    Sensor,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct AirDensityConfig {
+    pub iat_correction: IatCorrection,
+    // x: °C, y: % de combustible (100 = sin correccion)
+    pub iat_curve: Option<PlotData>,
+    // alpha-N: multiplica por baro / 101.3kPa
+    pub baro_correction: bool,
+    pub baro_source: BaroSource,
+    // kPa
+    pub fixed_baro: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum AccelSource {
+    Tps,
+    Map,
+    // el que pida mas
+    Both,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct AccelEnrichConfig {
+    pub enabled: bool,
+    pub source: AccelSource,
+    // %/s
+    pub tps_dot_threshold: f32,
+    // kPa/s
+    pub map_dot_threshold: f32,
+    // x: %/s, y: % agregado
+    pub tps_dot_curve: Option<PlotData>,
+    // x: kPa/s, y: % agregado
+    pub map_dot_curve: Option<PlotData>,
+    // ciclos hasta que el enriquecimiento baja a cero
+    pub decay_cycles: u32,
+    // x: °C, y: % del enriquecimiento que se aplica
+    pub clt_scale: Option<PlotData>,
+    // empobrecimiento (%) al cerrar por debajo de -threshold
+    pub decel_enleanment: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct FuelCutConfig {
+    pub enabled: bool,
+    pub min_rpm: i32,
+    // se vuelve a inyectar por debajo de min_rpm - rpm_hysteresis
+    pub rpm_hysteresis: i32,
+    // %, por encima se considera que se esta acelerando
+    pub tps_threshold: f32,
+    // °C
+    pub min_clt: f32,
+    // mS que se tienen que cumplir las condiciones antes de cortar
+    pub delay: u32,
+    // % agregado al volver a inyectar, baja a cero en reentry_cycles
+    pub reentry_enrichment: f32,
+    pub reentry_cycles: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct ClosedLoopConfig {
+    pub enabled: bool,
+    // % de correccion por unidad de error de lambda
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    // °C
+    pub min_clt: f32,
+    pub min_rpm: i32,
+    pub max_rpm: i32,
+    // segundos en RUNNING antes de habilitar
+    pub start_delay: u32,
+    // %, maxima correccion en cada sentido
+    pub max_trim: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct LtftConfig {
+    pub enabled: bool,
+    // %/s que se mueve la celda por cada % de correccion de lazo cerrado
+    pub learn_rate: f32,
+    // %, maximo que se puede mover cada celda
+    pub max_cell_trim: f32,
+    // segundos entre guardados en la flash con el motor en marcha, al pararse se guarda siempre
+    pub save_interval: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct CrankingConfig {
+    // x: °C, y: uS por ciclo mientras se arranca
+    pub pulse_width: Option<PlotData>,
+    // x: °C, y: uS del pulso de cebado al dar contacto
+    pub priming: Option<PlotData>,
+    // %, con TPS por encima mientras se arranca no se inyecta (motor ahogado)
+    pub flood_clear_tps: f32,
+    // ciclos en RUNNING para pasar del tiempo de arranque al normal
+    pub transition_cycles: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct FlexFuelConfig {
+    pub enabled: bool,
+    // Hz del sensor con 0% y 100% de etanol
+    pub freq_low: f32,
+    pub freq_high: f32,
+    pub stoich_ethanol: f32,
+    // x: % de etanol, y: % de combustible (100 = sin correccion)
+    pub fuel_correction: Option<PlotData>,
+    // % de etanol a usar si falla el sensor
+    pub fallback_ethanol: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone, PartialEq)]
+pub enum StagingMode {
+    // Tables.staging: % a los secundarios segun RPM / carga
+    Table,
+    // los secundarios entran cuando los primarios pasan duty_threshold
+    DutyCycle,
+}
+
+// inyeccion escalonada: primarios en iny_1, secundarios en iny_2
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct StagingConfig {
+    // cada salida es un grupo entero, asi que habilitado siempre inyecta en batch
+    // (los dos grupos en cada vuelta) y se ignora InjectionConfig.mode
+    pub enabled: bool,
+    pub mode: StagingMode,
+    // % de ciclo de trabajo de los primarios
+    pub duty_threshold: f32,
+    pub secondary: InjectorConfig,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FuelModel {
+    AlphaN,
+    SpeedDensity,
+}
+
 #[allow(non_snake_case)]
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
 pub struct InjectionConfig {
     pub target_lambda: f32,
     pub target_stoich: f32,
     pub enable_alphaN: bool,
     pub enable_speedDensity: bool,
+    // speed-density pasa a alpha-N con la MAP en falla
+    pub map_fault_alpha_n: bool,
     pub injector: InjectorConfig,
+
+    // con staging.enabled se ignora, corre en batch
+    pub mode: InjectionMode,
+    // angulo de fin de inyeccion, en grados desde el PMS del cilindro 1
+    // (si no esta cargada Tables.eoi)
+    pub eoi_angle: f32,
+    pub ve_load_axis: VeLoadAxis,
+    pub alpha_n: AlphaNConfig,
+    pub air_density: AirDensityConfig,
+    pub accel: AccelEnrichConfig,
+    pub fuel_cut: FuelCutConfig,
+    pub closed_loop: ClosedLoopConfig,
+    pub ltft: LtftConfig,
+    pub cranking: CrankingConfig,
+    pub flex_fuel: FlexFuelConfig,
+    pub staging: StagingConfig,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+impl InjectionConfig {
+    /**
+     * @brief modelo de combustible segun enable_alphaN / enable_speedDensity,
+     * None si estan los dos o ninguno
+     */
+    pub fn fuel_model(&self) -> Option<FuelModel> {
+        match (self.enable_alphaN, self.enable_speedDensity) {
+            (true, false) => Some(FuelModel::AlphaN),
+            (false, true) => Some(FuelModel::SpeedDensity),
+            _ => None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct IgnitionConfig {
+    // apagado no se manejan las bobinas, solo se calcula el avance
+    pub enabled: bool,
+    // uS de carga de la bobina
+    pub dwell: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, MaxSize, Debug, Copy, Clone)]
 pub struct EngineConfig {
     pub ready: bool,
     pub injection: InjectionConfig,
     pub engine: Engine,
+    pub ignition: IgnitionConfig,
 }
 
 impl EngineConfig {
     pub fn new() -> EngineConfig {
         get_default_efi_cfg()
     }
+
+    /**
+     * @brief false si hay cilindros que comparten inyector o bobina con correcciones distintas,
+     * con las salidas de la placa solo se puede aplicar el promedio de cada salida
+     */
+    pub fn cylinder_trims_supported(&self) -> bool {
+        let cylinders = self.engine.cylinder_count as usize;
+        let staged = self.injection.staging.enabled;
+
+        // sin decoder de CMP nunca hay fase
+        let (active_channels, _) = get_active_channels(effective_mode(self, false), cylinders, staged);
+        let injectors = get_trim_outputs(active_channels, staged);
+        let coils = get_coil_count(cylinders).max(1);
+
+        is_output_trim_uniform(&self.engine.cylinder_trim.fuel, cylinders, injectors)
+            && is_output_trim_uniform(&self.engine.cylinder_trim.spark, cylinders, coils)
+    }
 }
 
 
@@ -85,24 +353,126 @@ pub fn get_default_efi_cfg() -> EngineConfig {
                 sync_tooth_count: 0,
                 max_stall_time: 0,
             },
+            cylinder_trim: CylinderTrimConfig {
+                fuel: [100.0; MAX_CYLINDERS],
+                spark: [0.0; MAX_CYLINDERS],
+            },
         },
         injection: InjectionConfig {
             target_lambda: 1.1,
             target_stoich: 14.7,
             enable_alphaN: true,
             enable_speedDensity: false,
+            map_fault_alpha_n: true,
             injector: InjectorConfig {
                 flow_cc_min: 110.0,
                 injector_count: 4,
                 fuel_pressure: 1.0,
+                flow_ref_pressure: 1.0,
                 fuel_density: 0.726,
                 // promedio a "ojimetro" de:
                 // https://documents.holley.com/techlibrary_terminatorxv2injectordata.pdf
                 on_time: 750.0,
                 off_time: 250.0,
+                max_duty_cycle: 85.0,
+                min_pulse_width: 300.0,
+                small_pulse_correction: None,
                 // tabla correccion por bateria:
                 battery_correction: None,
             },
+            mode: InjectionMode::SemiSequential,
+            eoi_angle: 355.0,
+            ve_load_axis: VeLoadAxis::Map,
+            alpha_n: AlphaNConfig {
+                multiply_map: false,
+            },
+            air_density: AirDensityConfig {
+                iat_correction: IatCorrection::IdealGas,
+                iat_curve: None,
+                baro_correction: true,
+                baro_source: BaroSource::Map,
+                fixed_baro: 101.325,
+            },
+            accel: AccelEnrichConfig {
+                enabled: true,
+                source: AccelSource::Tps,
+                tps_dot_threshold: 40.0,
+                map_dot_threshold: 50.0,
+                tps_dot_curve: Some([
+                    [40, 1000], [100, 2000], [200, 3500], [400, 5000], [600, 6000],
+                    [800, 7000], [1000, 7500], [1500, 8000], [2000, 8500], [3000, 9000],
+                ]),
+                map_dot_curve: None,
+                decay_cycles: 10,
+                clt_scale: None,
+                decel_enleanment: 10.0,
+            },
+            fuel_cut: FuelCutConfig {
+                enabled: true,
+                min_rpm: 1800,
+                rpm_hysteresis: 300,
+                tps_threshold: 1.0,
+                min_clt: 60.0,
+                delay: 500,
+                reentry_enrichment: 15.0,
+                reentry_cycles: 20,
+            },
+            closed_loop: ClosedLoopConfig {
+                enabled: true,
+                kp: 20.0,
+                ki: 40.0,
+                kd: 0.0,
+                min_clt: 60.0,
+                min_rpm: 900,
+                max_rpm: 5000,
+                start_delay: 30,
+                max_trim: 15.0,
+            },
+            ltft: LtftConfig {
+                enabled: true,
+                learn_rate: 0.05,
+                max_cell_trim: 20.0,
+                save_interval: 1800,
+            },
+            cranking: CrankingConfig {
+                pulse_width: Some([
+                    [-40, 2_000_000], [-20, 1_600_000], [0, 1_200_000], [10, 1_000_000], [20, 850_000],
+                    [30, 700_000], [40, 600_000], [60, 450_000], [80, 350_000], [100, 300_000],
+                ]),
+                priming: None,
+                flood_clear_tps: 90.0,
+                transition_cycles: 10,
+            },
+            flex_fuel: FlexFuelConfig {
+                enabled: false,
+                freq_low: 50.0,
+                freq_high: 150.0,
+                stoich_ethanol: 9.0,
+                fuel_correction: None,
+                fallback_ethanol: 0.0,
+            },
+            staging: StagingConfig {
+                enabled: false,
+                mode: StagingMode::DutyCycle,
+                duty_threshold: 75.0,
+                secondary: InjectorConfig {
+                    flow_cc_min: 440.0,
+                    injector_count: 4,
+                    fuel_pressure: 3.0,
+                    flow_ref_pressure: 3.0,
+                    fuel_density: 0.726,
+                    on_time: 750.0,
+                    off_time: 250.0,
+                    max_duty_cycle: 85.0,
+                    min_pulse_width: 300.0,
+                    small_pulse_correction: None,
+                    battery_correction: None,
+                },
+            },
+        },
+        ignition: IgnitionConfig {
+            enabled: false,
+            dwell: 3000.0,
         },
     };
 
@@ -1,27 +1,42 @@
-use crate::app::engine::sensors::SensorValues;
+use serde::Serialize;
+
+use crate::app::{
+    engine::{diagnostics::Diagnostics, efi_cfg::MAX_CYLINDERS, sensors::SensorValues},
+    injection::{
+        accel::AccelStatus, closed_loop::ClosedLoopStatus, fuel_cut::FuelCutStatus, fuel_flow::TripComputer,
+        ltft::LtftStatus,
+    },
+};
 
 #[allow(non_camel_case_types)]
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum __rpm_status {
     STOPPED = 0, // < 50
     SPIN_UP,     // > 50
     CRANK,       // > 400 rpm
-    RUNNING,     // > 750
+    RUNNING,     // > 750, queda hasta que el motor se para
 }
 
 // #[allow(non_camel_case_types)]
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
 pub enum InjectionStatus {
     FuelCutoff = 0,
     FuelIdle,
     FullLoad,
     FuelAcc,
+    // condiciones de corte cumplidas, esperando el delay
+    FuelCutPending,
+    // volviendo a inyectar despues de un corte
+    FuelReentry,
+    FuelCranking,
+    // arrancando con el acelerador a fondo, sin inyeccion
+    FuelFloodClear,
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct InjectionInfo {
     pub targetAFR: f32,
     pub injection_bank_1_time: f32,
@@ -29,19 +44,71 @@ pub struct InjectionInfo {
     pub air_flow: f32,
     pub base_air: f32,
     pub base_fuel: f32,
+    // L/h
     pub fuel_flow_rate: f32,
+    // carga con la que se indexa la VE (TPS o MAP)
     pub fuel_load: f32,
+    // uS sumados a cada inyeccion
+    pub dead_time: f32,
+    // % (100 = sin correccion)
+    pub wue_correction: f32,
+    // % (100 = aire a 20°C)
+    pub iat_correction: f32,
+    // % (100 = 101.3kPa)
+    pub baro_correction: f32,
+    // % agregado
+    pub ase_correction: f32,
+    // % agregado (negativo al desacelerar)
+    pub accel_correction: f32,
+    pub target_lambda: f32,
+    // % de correccion de lazo cerrado
+    pub closed_loop_trim: f32,
+    // % de correccion aprendida
+    pub ltft_correction: f32,
+    // % del tiempo disponible por inyeccion en cada banco
+    pub duty_cycle_1: f32,
+    pub duty_cycle_2: f32,
+    // % del combustible en los secundarios
+    pub staging_share: f32,
+    // grados desde el PMS del cilindro 1
+    pub eoi_angle: f32,
+    pub stoich: f32,
+    // % (100 = sin correccion)
+    pub flex_correction: f32,
     pub injection_status: InjectionStatus,
 }
 
+#[derive(Serialize, Debug)]
+pub struct IgnitionInfo {
+    // grados APMS
+    pub advance: f32,
+    // avance de cada cilindro en orden de encendido, con su correccion
+    pub cylinder_advance: [f32; MAX_CYLINDERS],
+}
+
 #[derive(Debug)]
 pub struct EngineStatus {
     pub injection: InjectionInfo,
+    pub ignition: IgnitionInfo,
+    // ciclos desde que el motor paso a RUNNING
     pub cycle_tick: u32,
+    // vueltas desde que el motor paso a RUNNING
+    pub running_revolutions: u32,
+    // ultimo VRStatus.start_revolution visto, se reinicia al perder el sync
+    pub last_revolution: u128,
+    // uS por ciclo de 720°
     pub cycle_duration: f32,
     pub cycle_status: __rpm_status,
+    // referencia de fase (CMP) para inyectar en secuencial
+    pub has_phase: bool,
     pub rpm: i32,
     pub sensors: SensorValues,
+    pub accel: AccelStatus,
+    pub fuel_cut: FuelCutStatus,
+    pub closed_loop: ClosedLoopStatus,
+    pub ltft: LtftStatus,
+    pub diagnostics: Diagnostics,
+    pub trip: TripComputer,
 }
 
 pub fn get_default_engine_status() -> EngineStatus {
@@ -56,12 +123,40 @@ pub fn get_default_engine_status() -> EngineStatus {
             base_fuel: 0.0,
             fuel_flow_rate: 0.0,
             fuel_load: 0.0,
+            dead_time: 0.0,
+            wue_correction: 100.0,
+            iat_correction: 100.0,
+            baro_correction: 100.0,
+            ase_correction: 0.0,
+            accel_correction: 0.0,
+            target_lambda: 1.0,
+            closed_loop_trim: 0.0,
+            ltft_correction: 0.0,
+            duty_cycle_1: 0.0,
+            duty_cycle_2: 0.0,
+            staging_share: 0.0,
+            eoi_angle: 0.0,
+            stoich: 14.7,
+            flex_correction: 100.0,
             injection_status: InjectionStatus::FuelCutoff,
         },
+        ignition: IgnitionInfo {
+            advance: 0.0,
+            cylinder_advance: [0.0; MAX_CYLINDERS],
+        },
         cycle_tick: 0,
+        running_revolutions: 0,
+        last_revolution: 0,
         cycle_duration: 0.0,
         cycle_status: __rpm_status::STOPPED,
+        has_phase: false,
         rpm: 0,
+        accel: AccelStatus::new(),
+        fuel_cut: FuelCutStatus::new(),
+        closed_loop: ClosedLoopStatus::new(),
+        ltft: LtftStatus::new(),
+        diagnostics: Diagnostics::new(),
+        trip: TripComputer::new(),
     };
     return status;
 }
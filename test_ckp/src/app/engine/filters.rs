@@ -0,0 +1 @@
+pub use open_efi::math::filters::{FilterConfig, SensorFilter, SensorFiltersConfig, MAX_FILTER_SAMPLES};
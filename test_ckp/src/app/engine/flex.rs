@@ -0,0 +1,132 @@
+use crate::app::engine::{efi_cfg::FlexFuelConfig, sensors::SensorValues};
+
+// periodo del contador de captura (TIM10, 16 bits a 1MHz), en uS
+pub const CAPTURE_PERIOD: u32 = 65_536;
+
+// entre dos flancos mas que esto y los ticks ya dieron la vuelta, la proxima medicion arranca de cero
+const MAX_EDGE_GAP_MS: u32 = 60;
+
+// sin flancos por mas de esto el sensor esta desconectado
+const FLEX_TIMEOUT_MS: u32 = 500;
+
+// ancho del pulso -> temperatura del combustible (sensor tipo GM / Continental)
+const FUEL_TEMP_MIN_PULSE: f32 = 1000.0;
+const FUEL_TEMP_MAX_PULSE: f32 = 5000.0;
+const FUEL_TEMP_MIN: f32 = -40.0;
+const FUEL_TEMP_MAX: f32 = 125.0;
+
+// margen sobre freq_low / freq_high antes de dar la lectura por invalida, en Hz
+const FREQ_TOLERANCE: f32 = 5.0;
+
+#[derive(Debug, Copy, Clone)]
+pub struct FlexSensor {
+    // ticks de captura del ultimo flanco de subida / bajada, None hasta ver el primero
+    last_rise: Option<u32>,
+    last_fall: Option<u32>,
+    // mS del ultimo flanco
+    last_edge: u32,
+    // Hz
+    pub frequency: f32,
+    // uS en alto
+    pub pulse_width: u32,
+    // %
+    pub duty: f32,
+    // mS del ultimo periodo medido
+    pub updated_at: u32,
+    pub has_signal: bool,
+}
+
+impl FlexSensor {
+    pub fn new() -> FlexSensor {
+        FlexSensor {
+            last_rise: None,
+            last_fall: None,
+            last_edge: 0,
+            frequency: 0.0,
+            pulse_width: 0,
+            duty: 0.0,
+            updated_at: 0,
+            has_signal: false,
+        }
+    }
+
+    /**
+     * @brief se llama en cada captura de la entrada, `now` en ticks de TIM10 y `now_ms` en mS;
+     * el primer periodo se mide recien con dos flancos de subida seguidos
+     */
+    pub fn on_edge(&mut self, rising: bool, now: u32, now_ms: u32) {
+        if now_ms.wrapping_sub(self.last_edge) > MAX_EDGE_GAP_MS {
+            self.last_rise = None;
+            self.last_fall = None;
+        }
+        self.last_edge = now_ms;
+
+        if !rising {
+            self.last_fall = Some(now);
+            return;
+        }
+
+        let (last_rise, last_fall) = match (self.last_rise.replace(now), self.last_fall.take()) {
+            (Some(last_rise), Some(last_fall)) => (last_rise, last_fall),
+            _ => return,
+        };
+
+        let period = ticks_between(last_rise, now);
+        let high_time = ticks_between(last_rise, last_fall);
+
+        if period == 0 || high_time > period {
+            return;
+        }
+
+        self.frequency = 1_000_000.0 / period as f32;
+        self.pulse_width = high_time;
+        self.duty = high_time as f32 / period as f32 * 100.0;
+        self.updated_at = now_ms;
+        self.has_signal = true;
+    }
+
+    /**
+     * @brief pasa la ultima medicion a % de etanol y temperatura del combustible,
+     * false si no hay señal o esta fuera de rango (sensor desconectado, agua en el combustible)
+     */
+    pub fn update(&self, sensors: &mut SensorValues, cfg: &FlexFuelConfig, now: u32) -> bool {
+        let timed_out = now.wrapping_sub(self.updated_at) > FLEX_TIMEOUT_MS;
+
+        let ethanol = match get_ethanol_content(self.frequency, cfg) {
+            Some(ethanol) if self.has_signal && !timed_out => ethanol,
+            _ => {
+                sensors.ethanol = cfg.fallback_ethanol;
+                return false;
+            }
+        };
+
+        sensors.ethanol = ethanol;
+        sensors.fuel_temp = get_fuel_temperature(self.pulse_width);
+        true
+    }
+}
+
+fn ticks_between(from: u32, to: u32) -> u32 {
+    (to + CAPTURE_PERIOD - from) % CAPTURE_PERIOD
+}
+
+/**
+ * @brief % de etanol, lineal entre freq_low (0%) y freq_high (100%)
+ */
+pub fn get_ethanol_content(frequency: f32, cfg: &FlexFuelConfig) -> Option<f32> {
+    if frequency < cfg.freq_low - FREQ_TOLERANCE || frequency > cfg.freq_high + FREQ_TOLERANCE {
+        return None;
+    }
+
+    let span = (cfg.freq_high - cfg.freq_low).max(1.0);
+    Some(((frequency - cfg.freq_low) / span * 100.0).clamp(0.0, 100.0))
+}
+
+/**
+ * @brief temperatura del combustible (°C) segun el ancho del pulso
+ */
+pub fn get_fuel_temperature(pulse_width: u32) -> f32 {
+    let pulse = (pulse_width as f32).clamp(FUEL_TEMP_MIN_PULSE, FUEL_TEMP_MAX_PULSE);
+
+    FUEL_TEMP_MIN + (FUEL_TEMP_MAX - FUEL_TEMP_MIN) * (pulse - FUEL_TEMP_MIN_PULSE) / (FUEL_TEMP_MAX_PULSE - FUEL_TEMP_MIN_PULSE)
+}
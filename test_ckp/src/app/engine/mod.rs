@@ -1,7 +1,18 @@
+use crate::app::engine::engine_status::__rpm_status;
+
+pub mod aux_sensors;
+pub mod calibration;
 pub mod cpwm;
+pub mod diagnostics;
 pub mod efi_cfg;
+pub mod filters;
 pub mod engine_status;
+pub mod flex;
+pub mod sensor_cfg;
+pub mod sensor_faults;
 pub mod sensors;
+pub mod thermistor;
+pub mod tps_calibration;
 pub mod pmic;
 mod error;
 
@@ -18,3 +29,27 @@ pub fn get_degree_time(rpm: i32) -> f32 {
 pub fn get_engine_cycle_duration(rpm: i32) -> f32 {
     get_degree_time(rpm) / 2.0
 }
+
+/**
+ * @brief ciclos de 720° que pasan en `elapsed` mS a estas RPM
+ */
+pub fn get_elapsed_cycles(rpm: i32, elapsed: u32) -> f32 {
+    if rpm <= 0 {
+        return 0.0;
+    }
+
+    // un ciclo cada dos vueltas
+    elapsed as f32 * rpm as f32 / 120_000.0
+}
+
+/**
+ * @brief estado del motor segun las RPM, ver los umbrales en __rpm_status
+ */
+pub fn get_rpm_status(rpm: i32) -> __rpm_status {
+    match rpm {
+        i32::MIN..=49 => __rpm_status::STOPPED,
+        50..=400 => __rpm_status::SPIN_UP,
+        401..=750 => __rpm_status::CRANK,
+        _ => __rpm_status::RUNNING,
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    engine::{
+        aux_sensors::{AuxChannelConfig, AuxSensorType, AuxSensorsConfig, MUX_CHANNELS},
+        calibration::{get_map_preset, linear_between, Calibration, ADC_FULL_SCALE_MV},
+        filters::{FilterConfig, SensorFiltersConfig},
+        sensor_faults::{FaultConfig, SensorFaultsConfig},
+        thermistor::{get_coefficients, get_preset_points, SteinhartHart},
+    },
+    memory::tables::PlotData,
+};
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum MapPreset {
+    Gm1Bar,
+    Gm2Bar,
+    Gm3Bar,
+    Mpx4115,
+    Mpx4250,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum ThermistorMethod {
+    // tres puntos de calibracion
+    SteinhartHart,
+    // curva mV -> °C
+    Table,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum ThermistorPreset {
+    Gm,
+    Bosch,
+    Ford,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct ThermistorConfig {
+    pub method: ThermistorMethod,
+    // ohm, pull-up del divisor en la placa
+    pub bias_resistor: f32,
+    // [°C, ohm] para Steinhart-Hart
+    pub points: [[f32; 2]; 3],
+    // x: mV, y: °C
+    pub table: Option<PlotData>,
+
+    // salen de `points`, no se guardan
+    #[serde(skip)]
+    pub coefficients: Option<SteinhartHart>,
+}
+
+impl ThermistorConfig {
+    pub fn from_preset(preset: ThermistorPreset, bias_resistor: f32) -> ThermistorConfig {
+        let mut cfg = ThermistorConfig {
+            method: ThermistorMethod::SteinhartHart,
+            bias_resistor,
+            points: get_preset_points(preset),
+            table: None,
+            coefficients: None,
+        };
+        cfg.update_coefficients();
+        cfg
+    }
+
+    /**
+     * @brief resuelve Steinhart-Hart una sola vez, hay que llamarla cada vez que cambian los puntos
+     */
+    pub fn update_coefficients(&mut self) {
+        self.coefficients = Some(get_coefficients(&self.points));
+    }
+}
+
+// calibracion de los sensores, aparte de EngineConfig para que no se pierda al cambiar de mapa
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SensorConfig {
+    pub ready: bool,
+    pub clt: ThermistorConfig,
+    pub iat: ThermistorConfig,
+    // kPa
+    pub map: Calibration,
+    // kPa, sensor de baro propio (BaroSource::Sensor)
+    pub baro: Calibration,
+    // %, se limita a 0 - 100
+    pub tps: Calibration,
+    // V de bateria
+    pub batt: Calibration,
+    // lambda del controlador de banda ancha
+    pub lambda: Calibration,
+    pub faults: SensorFaultsConfig,
+    pub filters: SensorFiltersConfig,
+    // entradas del mux de ADC2
+    pub aux: AuxSensorsConfig,
+}
+
+// pull-up de CLT / IAT en la placa
+const BIAS_RESISTOR: f32 = 2490.0;
+
+// divisor resistivo de la entrada de bateria
+const VBAT_DIVIDER: f32 = 5.7;
+
+// el filtro que usaban todos los sensores
+const DEFAULT_FILTER: FilterConfig = FilterConfig::Ema { alpha: 0.45 };
+
+pub fn get_default_sensor_cfg() -> SensorConfig {
+    SensorConfig {
+        ready: false,
+        clt: ThermistorConfig::from_preset(ThermistorPreset::Gm, BIAS_RESISTOR),
+        iat: ThermistorConfig::from_preset(ThermistorPreset::Gm, BIAS_RESISTOR),
+        map: get_map_preset(MapPreset::Gm1Bar),
+        baro: get_map_preset(MapPreset::Mpx4115),
+        tps: linear_between(0.0, 0.0, ADC_FULL_SCALE_MV, 100.0),
+        batt: Calibration::Linear { offset: 0.0, gain: VBAT_DIVIDER / 1000.0 },
+        // salida lineal 0.5 - 1.5 en 0 - 3.3V
+        lambda: linear_between(0.0, 0.5, ADC_FULL_SCALE_MV, 1.5),
+        faults: SensorFaultsConfig {
+            map: FaultConfig { enabled: true, min_mv: 50.0, max_mv: 3250.0, persistence: 500, substitute: 101.3 },
+            tps: FaultConfig { enabled: true, min_mv: 50.0, max_mv: 3250.0, persistence: 200, substitute: 0.0 },
+            // sensor abierto: motor caliente
+            clt: FaultConfig { enabled: true, min_mv: 50.0, max_mv: 3250.0, persistence: 1000, substitute: 80.0 },
+            iat: FaultConfig { enabled: true, min_mv: 50.0, max_mv: 3250.0, persistence: 1000, substitute: 25.0 },
+            // con bateria baja la lectura es valida
+            batt: FaultConfig { enabled: false, min_mv: 0.0, max_mv: 3300.0, persistence: 1000, substitute: 13.5 },
+            // el controlador externo puede estar apagado al arrancar
+            lambda: FaultConfig { enabled: false, min_mv: 50.0, max_mv: 3250.0, persistence: 1000, substitute: 1.0 },
+        },
+        filters: SensorFiltersConfig {
+            map: DEFAULT_FILTER,
+            tps: DEFAULT_FILTER,
+            clt: DEFAULT_FILTER,
+            iat: DEFAULT_FILTER,
+            batt: DEFAULT_FILTER,
+            lambda: FilterConfig::MovingAverage { samples: 4 },
+        },
+        aux: AuxSensorsConfig {
+            enabled: false,
+            settle_time: 50,
+            interval: 20,
+            channels: [AuxChannelConfig {
+                sensor: AuxSensorType::None,
+                calibration: Calibration::Linear { offset: 0.0, gain: 1.0 },
+                filter: DEFAULT_FILTER,
+            }; MUX_CHANNELS],
+        },
+    }
+}
@@ -0,0 +1 @@
+pub use open_efi::math::sensor_faults::{FaultConfig, SensorFaults, SensorFaultsConfig};
@@ -1,116 +1,158 @@
+use crate::app::engine::{
+    aux_sensors::AuxSensorValues, calibration::apply_calibration, filters::SensorFilter, sensor_cfg::SensorConfig,
+    sensor_faults::SensorFaults, thermistor::get_temperature,
+};
 use crate::app::gpio::ADCMapping;
-use micromath::F32Ext;
 
 use stm32f4xx_hal::{
     adc::{config::SampleTime, Adc},
     pac::ADC2,
 };
 
-const EMA_LP_ALPHA: f32 = 0.45f32;
-
-pub enum SensorTypes {
-    MAP,
-    TPS,
-    CooltanTemp,
-    AirTemp,
-    BatteryVoltage,
-    ExternalLambda,
-}
+pub use open_efi::math::sensor_faults::{SensorTypes, SENSOR_COUNT};
 
 #[derive(Debug,Clone,Copy)]
 pub struct SensorValues {
     pub map: f32,
+    pub baro: f32,
+    // kPa del sensor de baro propio, 0 hasta la primera lectura (ver tasks/sensors.rs)
+    pub baro_sensor: f32,
     pub tps: f32,
     pub cooltan_temp: f32,
     pub air_temp: f32,
     pub batt: f32,
     pub ext_o2: f32,
+    pub lambda: f32,
+    // % de etanol y °C, del sensor de flex fuel (ver engine/flex.rs)
+    pub ethanol: f32,
+    pub fuel_temp: f32,
+    pub faults: SensorFaults,
+    // sensores del mux de ADC2 (ver tasks/sensors.rs)
+    pub aux: AuxSensorValues,
+
+    // mV en el pin de cada SensorTypes, sin filtrar y filtrados
+    pub raw: [f32; SENSOR_COUNT],
+    pub filtered: [f32; SENSOR_COUNT],
 
     // private:
-    raw_map: f32,
-    raw_tps: f32,
-    raw_temp: f32,
-    raw_air_temp: f32,
-    raw_batt: f32,
-    raw_ext_o2: f32,
+    filters: [SensorFilter; SENSOR_COUNT],
 }
 
 impl SensorValues {
     pub fn new() -> SensorValues {
         SensorValues {
             map: 0.0f32,
+            // hasta la primera lectura con el motor parado (ver air_density::update_baro)
+            baro: 101.325f32,
+            baro_sensor: 0.0f32,
             tps: 20.0f32,
             cooltan_temp: 45.69f32,
             air_temp: 0.0f32,
             batt: 13.42f32,
             ext_o2: 0.0f32,
-            // valores en raw son en bits del ADC; luego se pasan a mV
-            raw_map: 0.0f32,
-            raw_tps: 0.0f32,
-            raw_temp: 0.0f32,
-            raw_air_temp: 0.0f32,
-            raw_batt: 0.0f32,
-            raw_ext_o2: 0.0f32,
+            lambda: 1.0f32,
+            ethanol: 0.0f32,
+            fuel_temp: 0.0f32,
+            faults: SensorFaults::new(),
+            aux: AuxSensorValues::new(),
+            raw: [0.0f32; SENSOR_COUNT],
+            filtered: [0.0f32; SENSOR_COUNT],
+            filters: [SensorFilter::new(); SENSOR_COUNT],
         }
     }
 
-    // TODO: falta calcular cada valor del sensor ademas del low pass
-    pub fn update(&mut self, raw_value: u16, sensor_type: SensorTypes) {
-        match sensor_type {
-            SensorTypes::AirTemp => {
-                self.raw_air_temp = EMA_LP_ALPHA * (raw_value as f32)
-                    + (1.0 - EMA_LP_ALPHA) * (self.raw_air_temp as f32);
+    /**
+     * @brief lectura del sensor de baro propio, en mV
+     */
+    pub fn update_baro_sensor(&mut self, millivolts: u16, cfg: &SensorConfig) {
+        self.baro_sensor = apply_calibration(millivolts as f32, &cfg.baro);
+    }
 
-                // Ford ECC-IV type sensor
-                // TODO: make configurable
-                // FIXME: esta para el recontra culo la cuenta
-                let result = 143.73f32 * 0.99936f32.powf(self.raw_air_temp);
+    /**
+     * @brief mV filtrados del TPS, antes de la calibracion
+     */
+    pub fn raw_tps(&self) -> f32 {
+        self.filtered[SensorTypes::TPS as usize]
+    }
 
-                self.air_temp = result;
+    /**
+     * @brief `raw_value` en mV, `now` en mS para la persistencia de las fallas
+     */
+    pub fn update(&mut self, raw_value: u16, sensor_type: SensorTypes, cfg: &SensorConfig, now: u32) {
+        let index = sensor_type as usize;
+        let raw = raw_value as f32;
 
+        self.raw[index] = raw;
+        let millivolts = self.filters[index].apply(raw, cfg.filters.get(sensor_type));
+        self.filtered[index] = millivolts;
+
+        match sensor_type {
+            SensorTypes::AirTemp => {
+                // con el sensor abierto / en corto queda el ultimo valor
+                if let Some(temp) = get_temperature(millivolts, &cfg.iat) {
+                    self.air_temp = temp;
+                }
             }
             SensorTypes::CooltanTemp => {
-                self.raw_temp = EMA_LP_ALPHA * (raw_value as f32)
-                    + (1.0 - EMA_LP_ALPHA) * (self.raw_temp as f32);
+                if let Some(temp) = get_temperature(millivolts, &cfg.clt) {
+                    self.cooltan_temp = temp;
+                }
             }
             SensorTypes::MAP => {
-                self.raw_map = EMA_LP_ALPHA * (raw_value as f32)
-                    + (1.0 - EMA_LP_ALPHA) * (self.raw_map as f32);
+                self.map = apply_calibration(millivolts, &cfg.map);
             }
             SensorTypes::TPS => {
-                self.raw_tps = EMA_LP_ALPHA * (raw_value as f32)
-                    + (1.0 - EMA_LP_ALPHA) * (self.raw_tps as f32);
-
-                // SDUBTUD: tan fácil iba a ser?
-                let result = (24.6914 * self.raw_tps - 11.11f32).clamp(0f32,100f32);
-                self.tps = result;
+                self.tps = apply_calibration(millivolts, &cfg.tps).clamp(0f32, 100f32);
             }
             SensorTypes::BatteryVoltage => {
-                self.raw_batt = EMA_LP_ALPHA * (raw_value as f32)
-                    + (1.0 - EMA_LP_ALPHA) * (self.raw_batt as f32);
-
-                self.batt = 14.2;
+                self.batt = apply_calibration(millivolts, &cfg.batt);
             }
 
             SensorTypes::ExternalLambda => {
-                self.ext_o2 = raw_value as f32;
+                self.ext_o2 = millivolts;
+
+                self.lambda = apply_calibration(millivolts, &cfg.lambda);
             }
         }
+
+        // las fallas se detectan sobre la lectura sin filtrar
+        let fault = cfg.faults.get(sensor_type);
+        if self.faults.check(sensor_type, raw, fault, now) {
+            let value = match sensor_type {
+                SensorTypes::MAP => &mut self.map,
+                SensorTypes::TPS => &mut self.tps,
+                SensorTypes::CooltanTemp => &mut self.cooltan_temp,
+                SensorTypes::AirTemp => &mut self.air_temp,
+                SensorTypes::BatteryVoltage => &mut self.batt,
+                SensorTypes::ExternalLambda => &mut self.lambda,
+            };
+            *value = fault.substitute;
+        }
     }
 }
 
-pub fn get_sensor_raw(
-    sensor_type: SensorTypes,
-    adc_pins: &mut ADCMapping,
-    adc: &mut Adc<ADC2>,
-) -> u16 {
-    let a = sensor_type as u8;
-    let b = a;
-    let c = a;
-    adc_pins.mux_a.set_state(((a & (1 << 0)) != 0).into());
-    adc_pins.mux_b.set_state(((b & (1 << 1)) != 0).into());
-    adc_pins.mux_c.set_state(((c & (1 << 2)) != 0).into());
+/**
+ * @brief selecciona la entrada `channel` (0 - 7) del mux de ADC2, hay que esperar que asiente antes de leer
+ */
+pub fn select_mux_channel(channel: u8, adc_pins: &mut ADCMapping) {
+    adc_pins.mux_a.set_state(((channel & (1 << 0)) != 0).into());
+    adc_pins.mux_b.set_state(((channel & (1 << 1)) != 0).into());
+    adc_pins.mux_c.set_state(((channel & (1 << 2)) != 0).into());
+}
+
+/**
+ * @brief conecta (true) o suelta el sensor de baro de la entrada analogica, activo en bajo;
+ * mientras esta conectado no se puede leer el mux
+ */
+pub fn select_baro_sensor(select: bool, adc_pins: &mut ADCMapping) {
+    adc_pins.baro_cs.set_state((!select).into());
+}
+
+/**
+ * @brief mV en la salida del mux, del canal seleccionado
+ */
+pub fn get_mux_raw(adc_pins: &mut ADCMapping, adc: &mut Adc<ADC2>) -> u16 {
     let sample = adc.convert(&adc_pins.analog_in, SampleTime::Cycles_480);
 
-    return adc.sample_to_millivolts(sample);
+    adc.sample_to_millivolts(sample)
 }
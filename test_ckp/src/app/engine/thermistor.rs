@@ -0,0 +1,31 @@
+use crate::app::{
+    engine::sensor_cfg::{ThermistorConfig, ThermistorMethod, ThermistorPreset},
+    memory::tables::get_plot_value,
+};
+
+pub use open_efi::math::thermistor::{get_coefficients, get_resistance, SteinhartHart, THERMISTOR_SUPPLY_MV};
+
+/**
+ * @brief puntos [°C, ohm] de sensores comunes
+ */
+pub fn get_preset_points(preset: ThermistorPreset) -> [[f32; 2]; 3] {
+    match preset {
+        ThermistorPreset::Gm => [[-40.0, 100_700.0], [30.0, 2_238.0], [100.0, 177.0]],
+        ThermistorPreset::Bosch => [[-10.0, 9_397.0], [20.0, 2_500.0], [80.0, 323.0]],
+        ThermistorPreset::Ford => [[0.0, 95_850.0], [20.0, 37_300.0], [100.0, 2_070.0]],
+    }
+}
+
+/**
+ * @brief temperatura (°C) segun la calibracion, None si la lectura no es valida;
+ * los coeficientes de Steinhart-Hart se calculan al cargar la config (ThermistorConfig::update_coefficients)
+ */
+pub fn get_temperature(millivolts: f32, cfg: &ThermistorConfig) -> Option<f32> {
+    match cfg.method {
+        ThermistorMethod::Table => cfg.table.as_ref().map(|table| get_plot_value(table, millivolts)),
+        ThermistorMethod::SteinhartHart => {
+            let resistance = get_resistance(millivolts, cfg.bias_resistor)?;
+            cfg.coefficients?.temperature(resistance)
+        }
+    }
+}
@@ -0,0 +1 @@
+pub use open_efi::math::tps_calibration::{TpsCalibration, TpsCalibrationState, TpsCalibrationStep};
@@ -2,7 +2,7 @@
 
 use stm32f4xx_hal::{
     gpio::{
-        self, gpioa, gpiob, gpioc, gpiod, gpioe, Alternate, Analog, Input, Output, Pin, PushPull,
+        self, gpioa, gpiob, gpioc, gpiod, gpioe, Alternate, Analog, Input, Output, Pin, PinState, PushPull,
     },
     pac::SPI2,
     spi::Spi,
@@ -47,7 +47,8 @@ pub struct AuxIoMapping {
 
     pub in_5: gpio::PB5<Input>,
     pub in_6: gpio::PB7<Input>,
-    pub in_7: gpio::PB8<Input>,
+    // TIM10 CH1, entrada de captura del sensor de etanol
+    pub in_7: gpio::PB8<Alternate<3>>,
     pub in_8: gpio::PE1<Input>,
 
 
@@ -216,7 +217,7 @@ pub fn init_gpio(
             in_4: gpio_d.pd15.into_input(),
             in_5: gpio_b.pb5.into_input(),
             in_6: gpio_b.pb7.into_input(),
-            in_7: gpio_b.pb8.into_input(),
+            in_7: gpio_b.pb8.into_alternate(),
             in_8: gpio_e.pe1.into_input(),
 
             out_1: gpio_c.pc4.into_push_pull_output(),
@@ -248,7 +249,8 @@ pub fn init_gpio(
 
         adc: ADCMapping {
 
-            baro_cs: gpio_a.pa6.into_push_pull_output(),
+            // activo en bajo, arranca suelto para no pisar el mux
+            baro_cs: gpio_a.pa6.into_push_pull_output_in_state(PinState::High),
 
             mux_a: gpio_d.pd3.into_push_pull_output(),
             mux_b: gpio_d.pd4.into_push_pull_output(),
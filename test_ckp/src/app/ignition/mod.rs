@@ -0,0 +1,70 @@
+use stm32f4xx_hal::crc32::Crc32;
+use w25q::series25::FlashInfo;
+
+use crate::app::{
+    engine::{efi_cfg::EngineConfig, engine_status::EngineStatus},
+    injection::scheduler::get_output_trim,
+    memory::tables::{get_table_value, read_table, FlashT, Tables, ETHANOL_ADVANCE_SECTOR, LOAD_TPS_DEG_SECTOR},
+};
+
+pub mod scheduler;
+
+// salidas de encendido de la placa (ecn_1 / ecn_2)
+pub const IGNITION_OUTPUTS: usize = 2;
+
+/**
+ * @brief bobinas en uso con chispa perdida, 0 si el motor necesita mas de las que tiene la placa
+ */
+pub fn get_coil_count(cylinders: usize) -> usize {
+    let coils = (cylinders / 2).max(1);
+    if coils > IGNITION_OUTPUTS {
+        return 0;
+    }
+    coils
+}
+
+/**
+ * @brief carga desde la flash las tablas de avance, las que fallen el CRC quedan en None
+ */
+pub fn ignition_setup(tables: &mut Tables, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) {
+    tables.load_tps_deg = read_table(LOAD_TPS_DEG_SECTOR, flash, fi, crc);
+    tables.ethanol_advance = read_table(ETHANOL_ADVANCE_SECTOR, flash, fi, crc);
+}
+
+/**
+ * @brief avance (grados APMS) para RPM / TPS, con flex fuel se interpola
+ * entre la tabla de nafta y la de etanol segun el % de etanol
+ */
+pub fn calculate_advance(es: &mut EngineStatus, cfg: &EngineConfig, tables: &Tables) {
+    let rpm = es.rpm as f32;
+    let tps = es.sensors.tps;
+
+    let gasoline = match tables.load_tps_deg.as_ref() {
+        Some(table) => get_table_value(table, rpm, tps),
+        None => {
+            es.ignition.advance = 0.0;
+            es.ignition.cylinder_advance.fill(0.0);
+            return;
+        }
+    };
+
+    let advance = match tables.ethanol_advance.as_ref() {
+        Some(table) if cfg.injection.flex_fuel.enabled => {
+            let ethanol = get_table_value(table, rpm, tps);
+            gasoline + (ethanol - gasoline) * es.sensors.ethanol / 100.0
+        }
+        _ => gasoline,
+    };
+
+    es.ignition.advance = advance;
+
+    // con chispa perdida cada bobina enciende dos cilindros, los dos llevan el promedio de sus correcciones
+    let cylinders = cfg.engine.cylinder_count as usize;
+    let coils = match get_coil_count(cylinders) {
+        0 => cylinders,
+        coils => coils,
+    };
+    for (i, cylinder) in es.ignition.cylinder_advance.iter_mut().enumerate() {
+        *cylinder = advance + get_output_trim(&cfg.engine.cylinder_trim.spark, cylinders, i, coils);
+    }
+}
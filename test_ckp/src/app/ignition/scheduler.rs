@@ -0,0 +1,152 @@
+// Programacion del encendido por angulo, igual que la inyeccion (ver injection/scheduler.rs):
+// - injection_checks calcula el avance de cada cilindro y se lo pasa al scheduler con update()
+// - en cada diente del CKP on_tooth() programa las bobinas cuya carga empieza antes del proximo diente,
+//   la chispa va en el PMS del cilindro menos el avance y la carga arranca `dwell` antes (ver math/ignition.rs)
+// - TIM3 se arma con el evento mas cercano y on_timer() carga/dispara las bobinas con timer4 como referencia
+//
+// La placa tiene dos salidas de encendido y no hay fase del CMP, asi que siempre es chispa perdida:
+// cada bobina enciende cada 360° los cilindros que estan a 360° entre si (1-4 / 2-3 en un 4 cilindros).
+
+use crate::app::{
+    engine::{cpwm::VRStatus, efi_cfg::EngineConfig, engine_status::IgnitionInfo},
+    gpio::IgnitionGpioMapping,
+    ignition::{get_coil_count, IGNITION_OUTPUTS},
+    injection::scheduler::{is_due, ticks_add, ticks_until, MIN_TIMER_DELAY, TIMER_PERIOD},
+};
+
+pub use open_efi::math::ignition::{angle_to_time, angle_until, get_coil_offset, get_coil_timing};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CoilState {
+    Idle,
+    // esperando para empezar a cargar
+    Pending,
+    // cargando, la chispa sale al cortar
+    Dwell,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct IgnitionChannel {
+    pub state: CoilState,
+    // PMS de los cilindros de la bobina respecto al cilindro 1, en grados
+    pub offset: f32,
+    // grados APMS, con la correccion de sus cilindros
+    pub advance: f32,
+    // ticks de timer4
+    pub charge_at: u32,
+    pub spark_at: u32,
+}
+
+#[derive(Debug)]
+pub struct IgnitionScheduler {
+    pub channels: [IgnitionChannel; IGNITION_OUTPUTS],
+    pub active_channels: usize,
+    // uS de carga de la bobina
+    pub dwell: u32,
+}
+
+impl IgnitionChannel {
+    pub fn new() -> IgnitionChannel {
+        IgnitionChannel {
+            state: CoilState::Idle,
+            offset: 0.0,
+            advance: 0.0,
+            charge_at: 0,
+            spark_at: 0,
+        }
+    }
+}
+
+impl IgnitionScheduler {
+    pub fn new() -> IgnitionScheduler {
+        IgnitionScheduler {
+            channels: [IgnitionChannel::new(); IGNITION_OUTPUTS],
+            active_channels: 0,
+            dwell: 0,
+        }
+    }
+
+    /**
+     * @brief toma el avance de cada cilindro, las bobinas que ya estan programadas terminan con el anterior
+     */
+    pub fn update(&mut self, cfg: &EngineConfig, ignition: &IgnitionInfo) {
+        let coils = get_coil_count(cfg.engine.cylinder_count as usize);
+
+        self.active_channels = if cfg.ignition.enabled { coils } else { 0 };
+        self.dwell = cfg.ignition.dwell as u32;
+
+        for (i, channel) in self.channels[..coils].iter_mut().enumerate() {
+            channel.offset = get_coil_offset(i, coils);
+            // en orden de encendido la bobina i maneja los cilindros i, i + coils, ...
+            channel.advance = ignition.cylinder_advance[i];
+        }
+    }
+
+    /**
+     * @brief se llama en cada diente del CKP, programa las bobinas que tienen que empezar a cargar antes del proximo diente
+     */
+    pub fn on_tooth(&mut self, trigger: &VRStatus, crank_angle: f32, tooth_angle: f32, now: u32) {
+        let dwell = self.dwell;
+
+        for channel in self.channels[..self.active_channels].iter_mut() {
+            if channel.state != CoilState::Idle || dwell == 0 {
+                continue;
+            }
+
+            let timing = get_coil_timing(channel.offset, channel.advance, dwell, trigger.revolution_time);
+            let angle_to_charge = angle_until(timing.charge_angle, crank_angle);
+
+            // x2 para no perder el evento en el hueco del diente faltante
+            if angle_to_charge < tooth_angle * 2.0 {
+                let delay = angle_to_time(angle_to_charge, trigger.revolution_time);
+
+                channel.charge_at = ticks_add(now, delay);
+                channel.spark_at = ticks_add(channel.charge_at, dwell);
+                channel.state = CoilState::Pending;
+            }
+        }
+    }
+
+    /**
+     * @brief se llama desde la interrupcion de TIM3, carga/dispara las bobinas que ya vencieron;
+     * recorre todas para que una bobina cargando siempre termine aunque se desactive el encendido
+     */
+    pub fn on_timer(&mut self, now: u32, pins: &mut IgnitionGpioMapping) {
+        for i in 0..IGNITION_OUTPUTS {
+            let channel = self.channels[i];
+
+            if channel.state == CoilState::Pending && is_due(now, channel.charge_at) {
+                set_output(i, true, pins);
+                self.channels[i].state = CoilState::Dwell;
+            }
+
+            if self.channels[i].state == CoilState::Dwell && is_due(now, channel.spark_at) {
+                set_output(i, false, pins);
+                self.channels[i].state = CoilState::Idle;
+            }
+        }
+    }
+
+    /**
+     * @brief uS hasta el proximo evento programado, None si no hay nada pendiente
+     */
+    pub fn next_event(&self, now: u32) -> Option<u32> {
+        self.channels
+            .iter()
+            .filter_map(|channel| match channel.state {
+                CoilState::Pending => Some(ticks_until(now, channel.charge_at)),
+                CoilState::Dwell => Some(ticks_until(now, channel.spark_at)),
+                CoilState::Idle => None,
+            })
+            .map(|delay| if delay > TIMER_PERIOD / 2 { MIN_TIMER_DELAY } else { delay.max(MIN_TIMER_DELAY) })
+            .min()
+    }
+}
+
+fn set_output(channel: usize, charge: bool, pins: &mut IgnitionGpioMapping) {
+    match channel {
+        0 => pins.ecn_1.set_state(charge.into()),
+        1 => pins.ecn_2.set_state(charge.into()),
+        _ => {}
+    }
+}
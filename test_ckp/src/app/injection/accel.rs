@@ -0,0 +1,140 @@
+use crate::app::{
+    engine::{
+        efi_cfg::{AccelEnrichConfig, AccelSource},
+        engine_status::EngineStatus,
+        get_elapsed_cycles,
+    },
+    memory::tables::get_plot_value,
+};
+
+// cada cuanto se calculan TPSdot / MAPdot, con menos se amplifica el ruido del ADC
+const SAMPLE_TIME_MS: u32 = 20;
+
+#[derive(Debug, Copy, Clone)]
+pub struct AccelStatus {
+    // %/s
+    pub tps_dot: f32,
+    // kPa/s
+    pub map_dot: f32,
+    pub active: bool,
+    // % con el que arranco el enriquecimiento actual
+    pub enrichment: f32,
+    // ciclos desde que arranco el enriquecimiento actual, se cuentan aca con el tiempo y las RPM
+    pub elapsed_cycles: f32,
+
+    last_tps: f32,
+    last_map: f32,
+    last_time: u32,
+    last_decay: u32,
+}
+
+impl AccelStatus {
+    pub fn new() -> AccelStatus {
+        AccelStatus {
+            tps_dot: 0.0,
+            map_dot: 0.0,
+            active: false,
+            enrichment: 0.0,
+            elapsed_cycles: 0.0,
+            last_tps: 0.0,
+            last_map: 0.0,
+            last_time: 0,
+            last_decay: 0,
+        }
+    }
+
+    fn update_rates(&mut self, tps: f32, map: f32, now: u32) {
+        let elapsed = now.wrapping_sub(self.last_time);
+
+        if elapsed < SAMPLE_TIME_MS {
+            return;
+        }
+
+        let seconds = elapsed as f32 / 1000.0;
+        self.tps_dot = (tps - self.last_tps) / seconds;
+        self.map_dot = (map - self.last_map) / seconds;
+
+        self.last_tps = tps;
+        self.last_map = map;
+        self.last_time = now;
+    }
+}
+
+fn get_rate_enrichment(cfg: &AccelEnrichConfig, tps_dot: f32, map_dot: f32) -> f32 {
+    let tps = match cfg.tps_dot_curve.as_ref() {
+        Some(curve) if tps_dot > cfg.tps_dot_threshold => get_plot_value(curve, tps_dot),
+        _ => 0.0,
+    };
+    let map = match cfg.map_dot_curve.as_ref() {
+        Some(curve) if map_dot > cfg.map_dot_threshold => get_plot_value(curve, map_dot),
+        _ => 0.0,
+    };
+
+    match cfg.source {
+        AccelSource::Tps => tps,
+        AccelSource::Map => map,
+        AccelSource::Both => tps.max(map),
+    }
+}
+
+fn is_decelerating(cfg: &AccelEnrichConfig, tps_dot: f32, map_dot: f32) -> bool {
+    let tps = tps_dot < -cfg.tps_dot_threshold;
+    let map = map_dot < -cfg.map_dot_threshold;
+
+    match cfg.source {
+        AccelSource::Tps => tps,
+        AccelSource::Map => map,
+        AccelSource::Both => tps || map,
+    }
+}
+
+/**
+ * @brief enriquecimiento por aceleracion (% agregado, negativo al desacelerar),
+ * `now` en mS
+ */
+pub fn get_accel_enrichment(es: &mut EngineStatus, cfg: &AccelEnrichConfig, now: u32) -> f32 {
+    let (tps, map, clt, rpm) = (es.sensors.tps, es.sensors.map, es.sensors.cooltan_temp, es.rpm);
+    let accel = &mut es.accel;
+
+    accel.update_rates(tps, map, now);
+
+    let elapsed = now.wrapping_sub(accel.last_decay);
+    accel.last_decay = now;
+
+    if !cfg.enabled {
+        accel.active = false;
+        return 0.0;
+    }
+
+    let requested = get_rate_enrichment(cfg, accel.tps_dot, accel.map_dot);
+
+    // si se sigue pisando con mas fuerza se reinicia con el valor mas alto
+    if requested > 0.0 && (!accel.active || requested > accel.enrichment) {
+        accel.active = true;
+        accel.enrichment = requested;
+        accel.elapsed_cycles = 0.0;
+    } else if accel.active {
+        accel.elapsed_cycles += get_elapsed_cycles(rpm, elapsed);
+    }
+
+    if accel.active {
+        if accel.elapsed_cycles >= cfg.decay_cycles as f32 {
+            accel.active = false;
+            return 0.0;
+        }
+
+        let decay = 1.0 - accel.elapsed_cycles / cfg.decay_cycles as f32;
+        let clt_scale = match cfg.clt_scale.as_ref() {
+            Some(curve) => get_plot_value(curve, clt) / 100.0,
+            None => 1.0,
+        };
+
+        return accel.enrichment * decay * clt_scale;
+    }
+
+    if is_decelerating(cfg, accel.tps_dot, accel.map_dot) {
+        return -cfg.decel_enleanment;
+    }
+
+    0.0
+}
@@ -0,0 +1,82 @@
+use crate::app::{
+    engine::{
+        efi_cfg::{AirDensityConfig, BaroSource, IatCorrection},
+        sensors::SensorValues,
+    },
+    injection::speed_density::KELVIN,
+    memory::tables::get_plot_value,
+};
+
+// condiciones de referencia de la VE
+pub const STD_PRESSURE: f32 = 101.325;
+pub const STD_TEMPERATURE: f32 = 20.0;
+
+// fuera de este rango la lectura (MAP con el motor parado o sensor propio) no es una baro valida
+const BARO_MIN_KPA: f32 = 50.0;
+const BARO_MAX_KPA: f32 = 110.0;
+
+// gas ideal: la densidad es inversamente proporcional a la temperatura absoluta
+fn get_ideal_gas_correction(iat: f32) -> f32 {
+    (STD_TEMPERATURE + KELVIN) / (iat + KELVIN) * 100.0
+}
+
+/**
+ * @brief correccion por densidad del aire segun la IAT, en % (100 = aire a STD_TEMPERATURE)
+ */
+pub fn get_iat_correction(iat: f32, cfg: &AirDensityConfig) -> f32 {
+    match cfg.iat_correction {
+        IatCorrection::Off => 100.0,
+        IatCorrection::IdealGas => get_ideal_gas_correction(iat),
+        IatCorrection::Curve => get_iat_trim(iat, cfg),
+    }
+}
+
+/**
+ * @brief ajuste de la curva iat_curve en % (100 = sin ajuste), solo con IatCorrection::Curve
+ */
+pub fn get_iat_trim(iat: f32, cfg: &AirDensityConfig) -> f32 {
+    match cfg.iat_correction {
+        IatCorrection::Curve => cfg.iat_curve.as_ref().map_or(100.0, |curve| get_plot_value(curve, iat)),
+        IatCorrection::Off | IatCorrection::IdealGas => 100.0,
+    }
+}
+
+/**
+ * @brief correccion por IAT de speed-density en % (100 = aire a STD_TEMPERATURE): la densidad
+ * siempre sale de la IAT medida, en cualquier modo, y con Curve se suma el ajuste de la curva
+ */
+pub fn get_speed_density_iat_correction(iat: f32, cfg: &AirDensityConfig) -> f32 {
+    get_ideal_gas_correction(iat) * get_iat_trim(iat, cfg) / 100.0
+}
+
+/**
+ * @brief correccion por altura, en % (100 = STD_PRESSURE), solo para alpha-N:
+ * en speed-density la MAP es absoluta y ya incluye la altura
+ */
+pub fn get_baro_correction(baro: f32, cfg: &AirDensityConfig) -> f32 {
+    if !cfg.baro_correction || baro <= 0.0 {
+        return 100.0;
+    }
+
+    baro / STD_PRESSURE * 100.0
+}
+
+/**
+ * @brief actualiza la presion barometrica; de la MAP solo con el motor parado (`stopped`),
+ * con el motor girando ya no es la presion ambiente
+ */
+pub fn update_baro(sensors: &mut SensorValues, cfg: &AirDensityConfig, stopped: bool) {
+    let reading = match cfg.baro_source {
+        BaroSource::Fixed => {
+            sensors.baro = cfg.fixed_baro;
+            return;
+        }
+        BaroSource::Map if stopped => sensors.map,
+        BaroSource::Map => return,
+        BaroSource::Sensor => sensors.baro_sensor,
+    };
+
+    if reading >= BARO_MIN_KPA && reading <= BARO_MAX_KPA {
+        sensors.baro = reading;
+    }
+}
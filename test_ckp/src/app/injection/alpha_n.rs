@@ -0,0 +1,38 @@
+use crate::app::{
+    engine::{efi_cfg::EngineConfig, engine_status::EngineStatus},
+    injection::{
+        air_density::{get_baro_correction, get_iat_correction, STD_PRESSURE, STD_TEMPERATURE},
+        speed_density::get_air_mass,
+    },
+    memory::tables::{get_table_value, Tables},
+};
+
+pub fn get_ve(es: &EngineStatus, tables: &Tables) -> Option<f32> {
+    tables.tps_rpm_ve.as_ref().map(|ve| get_table_value(ve, es.rpm as f32, es.sensors.tps))
+}
+
+/**
+ * @brief masa de aire (mg) por alpha-N, con la VE indexada por TPS y RPM,
+ * None si no esta cargada la tabla de VE
+ */
+pub fn calculate_air_mass(es: &EngineStatus, cfg: &EngineConfig, tables: &Tables) -> Option<f32> {
+    let ve = get_ve(es, tables)?;
+
+    let mut air_mass = get_air_mass(
+        cfg.engine.displacement,
+        cfg.engine.cylinder_count,
+        ve,
+        STD_PRESSURE,
+        STD_TEMPERATURE,
+    );
+
+    if cfg.injection.alpha_n.multiply_map && es.sensors.baro > 0.0 {
+        air_mass *= es.sensors.map / es.sensors.baro;
+    }
+
+    let air_density = &cfg.injection.air_density;
+    let correction = get_iat_correction(es.sensors.air_temp, air_density) / 100.0
+        * get_baro_correction(es.sensors.baro, air_density) / 100.0;
+
+    Some(air_mass * correction)
+}
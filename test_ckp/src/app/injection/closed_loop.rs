@@ -0,0 +1,132 @@
+use crate::app::{
+    engine::{
+        efi_cfg::{ClosedLoopConfig, EngineConfig},
+        engine_status::{EngineStatus, __rpm_status},
+        sensors::SensorTypes,
+    },
+    injection::fuel_cut::FuelCutState,
+    memory::tables::{get_table_value, Tables},
+};
+
+#[derive(Debug, Copy, Clone)]
+pub struct ClosedLoopStatus {
+    pub active: bool,
+    // %
+    pub trim: f32,
+    // mS en el que el motor paso a RUNNING
+    pub running_since: Option<u32>,
+
+    integral: f32,
+    last_error: f32,
+    last_time: u32,
+}
+
+impl ClosedLoopStatus {
+    pub fn new() -> ClosedLoopStatus {
+        ClosedLoopStatus {
+            active: false,
+            trim: 0.0,
+            running_since: None,
+            integral: 0.0,
+            last_error: 0.0,
+            last_time: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.active = false;
+        self.trim = 0.0;
+        self.integral = 0.0;
+        self.last_error = 0.0;
+    }
+}
+
+/**
+ * @brief lambda objetivo desde tps_rpm_afr (AFR), si no esta cargada se usa target_lambda,
+ * el AFR se pasa a lambda con la estequiometrica de la mezcla actual (es.injection.stoich, con flex)
+ */
+pub fn get_target_lambda(es: &EngineStatus, cfg: &EngineConfig, tables: &Tables) -> f32 {
+    let stoich = es.injection.stoich;
+
+    let afr = match tables.tps_rpm_afr.as_ref() {
+        Some(afr) if stoich > 0.0 => get_table_value(afr, es.rpm as f32, es.sensors.tps),
+        _ => return cfg.injection.target_lambda,
+    };
+
+    // celda en cero o negativa: tabla mal cargada
+    if afr <= 0.0 {
+        return cfg.injection.target_lambda;
+    }
+
+    afr / stoich
+}
+
+fn is_enabled(es: &EngineStatus, cfg: &ClosedLoopConfig, now: u32) -> bool {
+    let running_time = match es.closed_loop.running_since {
+        Some(since) => now.wrapping_sub(since) / 1000,
+        None => 0,
+    };
+
+    cfg.enabled
+        && es.cycle_status == __rpm_status::RUNNING
+        && es.sensors.cooltan_temp >= cfg.min_clt
+        && es.rpm >= cfg.min_rpm
+        && es.rpm <= cfg.max_rpm
+        && running_time >= cfg.start_delay
+        && !es.sensors.faults.is_active(SensorTypes::ExternalLambda)
+}
+
+/**
+ * @brief PID sobre el error de lambda, devuelve la correccion en %,
+ * se congela durante el enriquecimiento por aceleracion y el corte de combustible, `now` en mS
+ */
+pub fn get_closed_loop_trim(es: &mut EngineStatus, cfg: &ClosedLoopConfig, target_lambda: f32, now: u32) -> f32 {
+    if es.cycle_status != __rpm_status::RUNNING {
+        es.closed_loop.running_since = None;
+    } else if es.closed_loop.running_since.is_none() {
+        es.closed_loop.running_since = Some(now);
+    }
+
+    let enabled = is_enabled(es, cfg, now);
+    let frozen = es.accel.active || es.fuel_cut.state != FuelCutState::Off;
+    let measured = es.sensors.lambda;
+    let status = &mut es.closed_loop;
+
+    if !enabled {
+        status.reset();
+        status.last_time = now;
+        return 0.0;
+    }
+
+    if frozen {
+        status.last_time = now;
+        return status.trim;
+    }
+
+    let dt = now.wrapping_sub(status.last_time) as f32 / 1000.0;
+    status.last_time = now;
+
+    // pobre (lambda medido > objetivo) => error positivo => mas combustible
+    let error = measured - target_lambda;
+
+    if !status.active {
+        status.active = true;
+        status.last_error = error;
+    }
+
+    if dt <= 0.0 {
+        return status.trim;
+    }
+
+    // anti-windup: el integral solo puede llegar al maximo de la correccion
+    if cfg.ki > 0.0 {
+        status.integral = (status.integral + error * dt).clamp(-cfg.max_trim / cfg.ki, cfg.max_trim / cfg.ki);
+    }
+
+    let derivative = (error - status.last_error) / dt;
+    status.last_error = error;
+
+    status.trim = (cfg.kp * error + cfg.ki * status.integral + cfg.kd * derivative).clamp(-cfg.max_trim, cfg.max_trim);
+
+    status.trim
+}
@@ -0,0 +1,56 @@
+use crate::app::{
+    engine::{
+        efi_cfg::CrankingConfig,
+        engine_status::{EngineStatus, InjectionStatus, __rpm_status},
+    },
+    memory::tables::get_plot_value,
+};
+
+/**
+ * @brief girando pero sin haber llegado a RUNNING desde la ultima detenida,
+ * RUNNING queda enganchado hasta el stall (ver update_cycle_status) asi que un motor en marcha
+ * que cae de RPM no vuelve a recibir el pulso de arranque ni el corte por flood clear
+ */
+pub fn is_cranking(es: &EngineStatus) -> bool {
+    match es.cycle_status {
+        __rpm_status::SPIN_UP | __rpm_status::CRANK => true,
+        __rpm_status::STOPPED | __rpm_status::RUNNING => false,
+    }
+}
+
+/**
+ * @brief pulso de cebado (uS) segun la temperatura, None si no esta configurado
+ */
+pub fn get_priming_pulse(clt: f32, cfg: &CrankingConfig) -> Option<u32> {
+    let pulse = get_plot_value(cfg.priming.as_ref()?, clt);
+
+    if pulse > 0.0 { Some(pulse as u32) } else { None }
+}
+
+/**
+ * @brief tiempo de inyeccion (uS por ciclo) mientras se arranca y en la transicion a RUNNING,
+ * `fuel_time` es el tiempo calculado por el modelo normal
+ */
+pub fn get_cranking_fuel(es: &mut EngineStatus, cfg: &CrankingConfig, fuel_time: f32) -> f32 {
+    let crank_time = match cfg.pulse_width.as_ref() {
+        Some(curve) => get_plot_value(curve, es.sensors.cooltan_temp).max(0.0),
+        None => return fuel_time,
+    };
+
+    if is_cranking(es) {
+        if es.sensors.tps >= cfg.flood_clear_tps {
+            es.injection.injection_status = InjectionStatus::FuelFloodClear;
+            return 0.0;
+        }
+
+        es.injection.injection_status = InjectionStatus::FuelCranking;
+        return crank_time;
+    }
+
+    if es.cycle_status == __rpm_status::RUNNING && es.cycle_tick < cfg.transition_cycles {
+        let blend = es.cycle_tick as f32 / cfg.transition_cycles as f32;
+        return crank_time + (fuel_time - crank_time) * blend;
+    }
+
+    fuel_time
+}
@@ -0,0 +1,40 @@
+use crate::app::{
+    engine::engine_status::{EngineStatus, __rpm_status},
+    memory::tables::{get_plot_value, Tables},
+};
+
+/**
+ * @brief enriquecimiento por temperatura (warm-up), en % (100 = sin correccion)
+ */
+pub fn get_wue(es: &EngineStatus, tables: &Tables) -> f32 {
+    match tables.wue.as_ref() {
+        Some(wue) => get_plot_value(wue, es.sensors.cooltan_temp).max(0.0),
+        None => 100.0,
+    }
+}
+
+/**
+ * @brief enriquecimiento post-arranque (ASE), en % agregado,
+ * arranca con la intensidad de ase_intensity y baja lineal hasta cero en los ciclos de ase_taper
+ */
+pub fn get_ase(es: &EngineStatus, tables: &Tables) -> f32 {
+    if es.cycle_status != __rpm_status::RUNNING {
+        return 0.0;
+    }
+
+    let (intensity, taper) = match (tables.ase_intensity.as_ref(), tables.ase_taper.as_ref()) {
+        (Some(intensity), Some(taper)) => (
+            get_plot_value(intensity, es.sensors.cooltan_temp),
+            get_plot_value(taper, es.sensors.cooltan_temp),
+        ),
+        _ => return 0.0,
+    };
+
+    let cycles = es.cycle_tick as f32;
+
+    if taper <= 0.0 || cycles >= taper {
+        return 0.0;
+    }
+
+    (intensity * (1.0 - cycles / taper)).max(0.0)
+}
@@ -0,0 +1,27 @@
+use crate::app::{
+    engine::efi_cfg::FlexFuelConfig,
+    memory::tables::get_plot_value,
+};
+
+/**
+ * @brief estequiometrica de la mezcla, interpolada entre nafta (target_stoich) y etanol
+ */
+pub fn get_stoich(gasoline_stoich: f32, cfg: &FlexFuelConfig, ethanol: f32) -> f32 {
+    if !cfg.enabled {
+        return gasoline_stoich;
+    }
+
+    gasoline_stoich + (cfg.stoich_ethanol - gasoline_stoich) * ethanol / 100.0
+}
+
+/**
+ * @brief correccion de combustible (%, 100 = sin correccion) segun el % de etanol,
+ * ademas de la que ya da la estequiometrica
+ */
+pub fn get_flex_fuel_correction(cfg: &FlexFuelConfig, ethanol: f32) -> f32 {
+    if !cfg.enabled {
+        return 100.0;
+    }
+
+    cfg.fuel_correction.as_ref().map_or(100.0, |curve| get_plot_value(curve, ethanol))
+}
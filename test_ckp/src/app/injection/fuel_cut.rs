@@ -0,0 +1,99 @@
+use crate::app::engine::{
+    efi_cfg::FuelCutConfig,
+    engine_status::{EngineStatus, InjectionStatus, __rpm_status},
+    get_elapsed_cycles,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FuelCutState {
+    Off,
+    Pending,
+    Active,
+    Reentry,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FuelCutStatus {
+    pub state: FuelCutState,
+    // mS en el que se cumplieron las condiciones
+    pub since: u32,
+    // ciclos desde que se volvio a inyectar, se cuentan aca con el tiempo y las RPM
+    pub reentry_elapsed: f32,
+
+    last_time: u32,
+}
+
+impl FuelCutStatus {
+    pub fn new() -> FuelCutStatus {
+        FuelCutStatus {
+            state: FuelCutState::Off,
+            since: 0,
+            reentry_elapsed: 0.0,
+            last_time: 0,
+        }
+    }
+}
+
+fn can_cut(es: &EngineStatus, cfg: &FuelCutConfig) -> bool {
+    cfg.enabled
+        && es.cycle_status == __rpm_status::RUNNING
+        && es.rpm > cfg.min_rpm
+        && es.sensors.tps <= cfg.tps_threshold
+        && es.sensors.cooltan_temp >= cfg.min_clt
+}
+
+fn must_resume(es: &EngineStatus, cfg: &FuelCutConfig) -> bool {
+    !cfg.enabled
+        || es.cycle_status != __rpm_status::RUNNING
+        || es.rpm < cfg.min_rpm - cfg.rpm_hysteresis
+        || es.sensors.tps > cfg.tps_threshold
+}
+
+/**
+ * @brief maquina de estados del corte en desaceleracion, devuelve el multiplicador de combustible
+ * (0 cortado, > 1 durante el reingreso) y deja el estado en injection_status, `now` en mS
+ */
+pub fn get_fuel_cut_correction(es: &mut EngineStatus, cfg: &FuelCutConfig, now: u32) -> f32 {
+    let cut = can_cut(es, cfg);
+    let resume = must_resume(es, cfg);
+    let rpm = es.rpm;
+    let status = &mut es.fuel_cut;
+
+    let elapsed = now.wrapping_sub(status.last_time);
+    status.last_time = now;
+    if status.state == FuelCutState::Reentry {
+        status.reentry_elapsed += get_elapsed_cycles(rpm, elapsed);
+    }
+
+    status.state = match status.state {
+        FuelCutState::Off | FuelCutState::Reentry if cut => {
+            status.since = now;
+            FuelCutState::Pending
+        }
+        FuelCutState::Pending if !cut => FuelCutState::Off,
+        FuelCutState::Pending if now.wrapping_sub(status.since) >= cfg.delay => FuelCutState::Active,
+        FuelCutState::Active if resume => {
+            status.reentry_elapsed = 0.0;
+            FuelCutState::Reentry
+        }
+        FuelCutState::Reentry if status.reentry_elapsed >= cfg.reentry_cycles as f32 => FuelCutState::Off,
+        state => state,
+    };
+
+    match status.state {
+        FuelCutState::Off => 1.0,
+        FuelCutState::Pending => {
+            es.injection.injection_status = InjectionStatus::FuelCutPending;
+            1.0
+        }
+        FuelCutState::Active => {
+            es.injection.injection_status = InjectionStatus::FuelCutoff;
+            0.0
+        }
+        FuelCutState::Reentry => {
+            es.injection.injection_status = InjectionStatus::FuelReentry;
+            let ramp = 1.0 - status.reentry_elapsed / cfg.reentry_cycles.max(1) as f32;
+            1.0 + cfg.reentry_enrichment / 100.0 * ramp.max(0.0)
+        }
+    }
+}
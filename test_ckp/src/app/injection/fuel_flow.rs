@@ -0,0 +1,108 @@
+use postcard::experimental::max_size::MaxSize;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{
+    engine::{efi_cfg::EngineConfig, engine_status::EngineStatus},
+    injection::injectors::{get_dead_time, get_injector_flow},
+};
+
+// entre dos llamadas, mas que esto se descarta (task frenada, reinicio del contador)
+const MAX_STEP_MS: u32 = 1000;
+
+// se publica solo por USB (realtime), la exportacion por CAN queda para cuando haya driver de CAN
+#[derive(Serialize, Deserialize, MaxSize, Debug, Copy, Clone)]
+pub struct TripComputer {
+    // mL consumidos desde el ultimo reset
+    pub trip_fuel: u32,
+    // mL consumidos en total
+    pub total_fuel: u32,
+
+    // fraccion de mL que todavia no se sumo
+    #[serde(skip)]
+    pending: f32,
+    // hay cambios sin guardar en la flash
+    #[serde(skip)]
+    pub dirty: bool,
+    // secuencia de la ultima copia en la flash (ver memory/rotating.rs)
+    #[serde(skip)]
+    pub sequence: u32,
+    #[serde(skip)]
+    last_update: u32,
+}
+
+impl TripComputer {
+    pub fn new() -> TripComputer {
+        TripComputer {
+            trip_fuel: 0,
+            total_fuel: 0,
+            pending: 0.0,
+            dirty: false,
+            sequence: 0,
+            last_update: 0,
+        }
+    }
+
+    /**
+     * @brief borra el parcial (comando de reset), se guarda con el motor parado
+     */
+    pub fn reset_trip(&mut self) {
+        self.trip_fuel = 0;
+        self.dirty = true;
+    }
+
+    /**
+     * @brief integra el caudal (L/h), `now` en mS
+     */
+    pub fn update(&mut self, fuel_flow_rate: f32, now: u32) {
+        let elapsed = now.wrapping_sub(self.last_update);
+        self.last_update = now;
+
+        if elapsed > MAX_STEP_MS || fuel_flow_rate <= 0.0 {
+            return;
+        }
+
+        // L/h -> mL en `elapsed` mS
+        self.pending += fuel_flow_rate * elapsed as f32 / 3600.0;
+
+        if self.pending >= 1.0 {
+            let ml = self.pending as u32;
+            self.pending -= ml as f32;
+            self.trip_fuel = self.trip_fuel.saturating_add(ml);
+            self.total_fuel = self.total_fuel.saturating_add(ml);
+            self.dirty = true;
+        }
+    }
+}
+
+/**
+ * @brief caudal de combustible instantaneo (L/h) a partir del tiempo abierto de los inyectores,
+ * sin el tiempo muerto
+ */
+pub fn get_fuel_flow_rate(es: &EngineStatus, cfg: &EngineConfig, injections_per_cycle: u32) -> f32 {
+    let primary = &cfg.injection.injector;
+    let cycles_per_second = es.rpm.max(0) as f32 / 120.0;
+
+    let bank_1 = (es.injection.injection_bank_1_time - es.injection.dead_time).max(0.0);
+    let bank_2 = (es.injection.injection_bank_2_time - es.injection.dead_time).max(0.0);
+
+    // mg por inyeccion de todos los inyectores
+    let fuel = if cfg.injection.staging.enabled {
+        let secondary = &cfg.injection.staging.secondary;
+        let secondary_dead_time = get_dead_time(es.sensors.batt, secondary, None);
+        let bank_2 = (es.injection.injection_bank_2_time - secondary_dead_time).max(0.0);
+
+        bank_1 * get_injector_flow(primary) * primary.injector_count as f32
+            + bank_2 * get_injector_flow(secondary) * secondary.injector_count as f32
+    } else {
+        // la mitad de los inyectores en cada salida (en batch los dos bancos son iguales)
+        (bank_1 + bank_2) / 2.0 * get_injector_flow(primary) * primary.injector_count as f32
+    };
+
+    if primary.fuel_density <= 0.0 {
+        return 0.0;
+    }
+
+    // mg/s -> L/h
+    let mg_per_second = fuel * injections_per_cycle as f32 * cycles_per_second;
+    mg_per_second * 3600.0 / (primary.fuel_density * 1_000_000.0)
+}
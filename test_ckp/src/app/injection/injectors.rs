@@ -0,0 +1,68 @@
+use open_efi::math::injectors as injector_math;
+
+use crate::app::{
+    engine::efi_cfg::InjectorConfig,
+    memory::tables::{get_plot_value, PlotData},
+};
+
+pub use open_efi::math::injectors::{get_available_time, get_duty_cycle};
+
+/**
+ * @brief caudal del inyector en mg/uS, corregido por la presion de combustible
+ */
+pub fn get_injector_flow(injector: &InjectorConfig) -> f32 {
+    injector_math::get_injector_flow(injector.flow_cc_min, injector.fuel_density, injector.fuel_pressure, injector.flow_ref_pressure)
+}
+
+/**
+ * @brief tiempo de inyeccion (uS) por inyector por ciclo para entregar `fuel_mass` (mg) a cada cilindro
+ */
+pub fn get_pulse_width(fuel_mass: f32, injector: &InjectorConfig, cylinder_count: u8) -> f32 {
+    injector_math::get_pulse_width(fuel_mass, get_injector_flow(injector), injector.injector_count, cylinder_count)
+}
+
+/**
+ * @brief pulso (uS) que entrega con los inyectores `to` el mismo combustible que `pulse` con los `from`
+ */
+pub fn convert_pulse(pulse: f32, from: &InjectorConfig, to: &InjectorConfig) -> f32 {
+    let to_flow = get_injector_flow(to) * to.injector_count as f32;
+
+    if to_flow <= 0.0 {
+        return 0.0;
+    }
+
+    pulse * get_injector_flow(from) * from.injector_count as f32 / to_flow
+}
+
+/**
+ * @brief tiempo de inyeccion (uS) para `air_mass` (mg por cilindro) con la mezcla objetivo
+ */
+pub fn get_required_fuel(air_mass: f32, stoich: f32, lambda: f32, injector: &InjectorConfig, cylinder_count: u8) -> f32 {
+    let flow = get_injector_flow(injector);
+    injector_math::get_required_fuel(air_mass, stoich, lambda, flow, injector.injector_count, cylinder_count)
+}
+
+/**
+ * @brief tiempo muerto del inyector (uS) a la tension de bateria (V),
+ * si no hay curva se usa on_time - off_time
+ */
+pub fn get_dead_time(battery: f32, injector: &InjectorConfig, curve: Option<&PlotData>) -> f32 {
+    match curve.or(injector.battery_correction.as_ref()) {
+        Some(curve) => get_plot_value(curve, battery * 1000.0).max(0.0),
+        None => (injector.on_time - injector.off_time).max(0.0),
+    }
+}
+
+/**
+ * @brief pulso final (uS) de una inyeccion: tiempo efectivo + tiempo muerto + correccion de pulsos cortos,
+ * 0 si el tiempo efectivo no llega al minimo del inyector
+ */
+pub fn get_injection_pulse(fuel_pulse: f32, dead_time: f32, injector: &InjectorConfig) -> f32 {
+    if fuel_pulse <= 0.0 || fuel_pulse < injector.min_pulse_width {
+        return 0.0;
+    }
+
+    let small_pulse = injector.small_pulse_correction.as_ref().map_or(0.0, |curve| get_plot_value(curve, fuel_pulse));
+
+    (fuel_pulse + dead_time + small_pulse).max(0.0)
+}
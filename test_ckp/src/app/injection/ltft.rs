@@ -0,0 +1,85 @@
+use crate::app::{
+    engine::{efi_cfg::LtftConfig, engine_status::EngineStatus},
+    injection::fuel_cut::FuelCutState,
+    memory::tables::{get_table_value, Tables},
+};
+
+pub use open_efi::math::ltft::{clear_ltft, get_empty_ltft, learn_ltft};
+
+// las celdas son enteras, aprendiendo en cada llamada el paso se redondea a cero
+const LEARN_INTERVAL_MS: u32 = 1000;
+
+#[derive(Debug, Copy, Clone)]
+pub struct LtftStatus {
+    // hay cambios sin guardar en la flash
+    pub dirty: bool,
+    // secuencia de la ultima copia en la flash (ver memory/rotating.rs)
+    pub sequence: u32,
+    last_learn: u32,
+}
+
+impl LtftStatus {
+    pub fn new() -> LtftStatus {
+        LtftStatus {
+            dirty: false,
+            sequence: 0,
+            last_learn: 0,
+        }
+    }
+}
+
+/**
+ * @brief si no hay correcciones guardadas arranca en cero con los ejes de la VE
+ */
+pub fn init_ltft(tables: &mut Tables) {
+    if tables.ltft.is_some() {
+        return;
+    }
+
+    if let Some(ve) = tables.tps_rpm_ve.as_ref() {
+        tables.ltft = Some(get_empty_ltft(ve));
+    }
+}
+
+/**
+ * @brief borra lo aprendido (comando de reset), se guarda con el motor parado
+ */
+pub fn reset_ltft(tables: &mut Tables, status: &mut LtftStatus) {
+    if let Some(ltft) = tables.ltft.as_mut() {
+        clear_ltft(ltft);
+        status.dirty = true;
+    }
+}
+
+/**
+ * @brief correccion aprendida (%) para RPM / carga, aprende de la correccion de lazo cerrado
+ * mientras esta activa, `now` en mS
+ */
+pub fn get_ltft(es: &mut EngineStatus, cfg: &LtftConfig, tables: &mut Tables, load: f32, now: u32) -> f32 {
+    let ltft = match tables.ltft.as_mut() {
+        Some(ltft) => ltft,
+        None => return 0.0,
+    };
+
+    let rpm = es.rpm as f32;
+    let learning = es.closed_loop.active && !es.accel.active && es.fuel_cut.state == FuelCutState::Off;
+
+    let elapsed = now.wrapping_sub(es.ltft.last_learn);
+
+    if !learning {
+        es.ltft.last_learn = now;
+    } else if cfg.enabled && elapsed >= LEARN_INTERVAL_MS {
+        es.ltft.last_learn = now;
+
+        let elapsed = elapsed as f32 / 1000.0;
+        if learn_ltft(ltft, rpm, load, es.closed_loop.trim, cfg.learn_rate, elapsed, cfg.max_cell_trim) {
+            es.ltft.dirty = true;
+        }
+    }
+
+    if !cfg.enabled {
+        return 0.0;
+    }
+
+    get_table_value(ltft, rpm, load)
+}
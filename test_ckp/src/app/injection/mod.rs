@@ -0,0 +1,242 @@
+use stm32f4xx_hal::crc32::Crc32;
+use w25q::series25::FlashInfo;
+
+use crate::app::{
+    engine::{
+        diagnostics::DiagnosticCode,
+        efi_cfg::{EngineConfig, FuelModel, VeLoadAxis},
+        engine_status::{EngineStatus, InjectionStatus},
+        sensors::SensorTypes,
+    },
+    injection::{
+        accel::get_accel_enrichment,
+        air_density::{get_baro_correction, get_iat_correction, get_speed_density_iat_correction},
+        closed_loop::{get_closed_loop_trim, get_target_lambda},
+        cranking::get_cranking_fuel,
+        enrichment::{get_ase, get_wue},
+        flex_fuel::{get_flex_fuel_correction, get_stoich},
+        fuel_flow::get_fuel_flow_rate,
+        fuel_cut::get_fuel_cut_correction,
+        injectors::{get_available_time, get_dead_time, get_duty_cycle, get_injection_pulse, get_required_fuel},
+        ltft::{get_ltft, init_ltft, LtftStatus},
+        scheduler::{effective_mode, get_bank_times, get_injections_per_cycle},
+        staging::get_staged_times,
+    },
+    logging::host,
+    memory::tables::{
+        get_table_value, read_ltft, read_plot, read_table, FlashT, Tables, ASE_INTENSITY_SECTOR, ASE_TAPER_SECTOR,
+        EOI_SECTOR, STAGING_SECTOR, TPS_RPM_AFR_SECTOR, TPS_RPM_VE_SECTOR, VBAT_CORRECTION_SECTOR, WUE_SECTOR,
+    },
+};
+
+pub mod accel;
+pub mod air_density;
+pub mod alpha_n;
+pub mod closed_loop;
+pub mod cranking;
+pub mod enrichment;
+pub mod flex_fuel;
+pub mod fuel_flow;
+pub mod fuel_cut;
+pub mod injectors;
+pub mod ltft;
+pub mod scheduler;
+pub mod speed_density;
+pub mod staging;
+
+/**
+ * @brief carga desde la flash las tablas de inyeccion, las que fallen el CRC quedan en None
+ */
+pub fn injection_setup(tables: &mut Tables, ltft: &mut LtftStatus, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) {
+    tables.tps_rpm_ve = read_table(TPS_RPM_VE_SECTOR, flash, fi, crc);
+    tables.tps_rpm_afr = read_table(TPS_RPM_AFR_SECTOR, flash, fi, crc);
+    tables.vbat_correction = read_plot(VBAT_CORRECTION_SECTOR, flash, fi, crc);
+    tables.wue = read_plot(WUE_SECTOR, flash, fi, crc);
+    tables.ase_taper = read_plot(ASE_TAPER_SECTOR, flash, fi, crc);
+    tables.ase_intensity = read_plot(ASE_INTENSITY_SECTOR, flash, fi, crc);
+    if let Some((data, sequence)) = read_ltft(flash, fi, crc) {
+        tables.ltft = Some(data);
+        ltft.sequence = sequence;
+    }
+    tables.staging = read_table(STAGING_SECTOR, flash, fi, crc);
+    tables.eoi = read_table(EOI_SECTOR, flash, fi, crc);
+    init_ltft(tables);
+
+    if tables.tps_rpm_ve.is_none() {
+        host::debug!("tabla VE no disponible");
+    }
+}
+
+// por debajo de este TPS (%) se considera mariposa cerrada
+pub const IDLE_TPS: f32 = 1.0;
+
+/**
+ * @brief calcula el tiempo de inyeccion de cada banco, el scheduler lo toma en cada diente del CKP,
+ * `now` en mS
+ */
+pub fn calculate_time_isr(es: &mut EngineStatus, cfg: &EngineConfig, tables: &mut Tables, now: u32) {
+    if es.rpm <= 0 {
+        es.injection.injection_bank_1_time = 0.0;
+        es.injection.injection_bank_2_time = 0.0;
+        es.injection.fuel_flow_rate = 0.0;
+        es.injection.injection_status = InjectionStatus::FuelCutoff;
+        return;
+    }
+
+    es.injection.fuel_load = get_fuel_load(es, cfg);
+
+    es.injection.injection_status = if es.sensors.tps <= IDLE_TPS { InjectionStatus::FuelIdle } else { InjectionStatus::FullLoad };
+
+    let fuel_model = get_fuel_model(es, cfg);
+    es.injection.iat_correction = match fuel_model {
+        Some(FuelModel::SpeedDensity) => get_speed_density_iat_correction(es.sensors.air_temp, &cfg.injection.air_density),
+        _ => get_iat_correction(es.sensors.air_temp, &cfg.injection.air_density),
+    };
+    es.injection.baro_correction = match fuel_model {
+        Some(FuelModel::AlphaN) => get_baro_correction(es.sensors.baro, &cfg.injection.air_density),
+        _ => 100.0,
+    };
+
+    let air_mass = match fuel_model {
+        Some(FuelModel::AlphaN) => alpha_n::calculate_air_mass(es, cfg, tables),
+        Some(FuelModel::SpeedDensity) => speed_density::calculate_air_mass(es, cfg, tables),
+        // config invalida (alpha-N y speed-density a la vez, o ninguno): sin combustible
+        None => None,
+    };
+
+    let air_mass = air_mass.unwrap_or(0.0);
+    // la estequiometrica va primero, la lambda objetivo sale de ella
+    es.injection.stoich = get_stoich(cfg.injection.target_stoich, &cfg.injection.flex_fuel, es.sensors.ethanol);
+    let target_lambda = get_target_lambda(es, cfg, tables);
+    es.injection.target_lambda = target_lambda;
+    set_base_fuel(es, cfg, air_mass, target_lambda);
+
+    let mut fuel_time = get_required_fuel(
+        air_mass,
+        es.injection.stoich,
+        target_lambda,
+        &cfg.injection.injector,
+        cfg.engine.cylinder_count,
+    );
+
+    // correcciones
+    let wue = get_wue(es, tables);
+    let ase = get_ase(es, tables);
+    es.injection.wue_correction = wue;
+    es.injection.ase_correction = ase;
+
+    let accel = get_accel_enrichment(es, &cfg.injection.accel, now);
+    es.injection.accel_correction = accel;
+    if es.accel.active {
+        es.injection.injection_status = InjectionStatus::FuelAcc;
+    }
+
+    let flex = get_flex_fuel_correction(&cfg.injection.flex_fuel, es.sensors.ethanol);
+    es.injection.flex_correction = flex;
+
+    fuel_time *= (wue / 100.0) * (1.0 + ase / 100.0) * (1.0 + accel / 100.0) * (flex / 100.0);
+
+    // corte en desaceleracion, pisa el estado de arriba
+    fuel_time *= get_fuel_cut_correction(es, &cfg.injection.fuel_cut, now);
+
+    // lazo cerrado, despues del corte para poder congelarse
+    let trim = get_closed_loop_trim(es, &cfg.injection.closed_loop, target_lambda, now);
+    es.injection.closed_loop_trim = trim;
+    fuel_time *= 1.0 + trim / 100.0;
+
+    let ltft = get_ltft(es, &cfg.injection.ltft, tables, get_fuel_load(es, cfg), now);
+    es.injection.ltft_correction = ltft;
+    fuel_time *= 1.0 + ltft / 100.0;
+
+    // arranque: reemplaza todo lo anterior
+    fuel_time = get_cranking_fuel(es, &cfg.injection.cranking, fuel_time);
+
+    let mode = effective_mode(cfg, es.has_phase);
+    es.injection.eoi_angle = get_eoi_angle(es, cfg, tables);
+    es.cycle_duration = get_available_time(es.rpm, 1);
+    let available_time = get_available_time(es.rpm, get_injections_per_cycle(mode));
+
+    let (bank_1, bank_2) = get_bank_times(mode, fuel_time);
+
+    // escalonada: el banco 2 son los secundarios, con su propio inyector
+    let primary = &cfg.injection.injector;
+    es.injection.staging_share = 0.0;
+    let (bank_2_injector, (bank_1, bank_2)) = match cfg.injection.staging.enabled {
+        true => (&cfg.injection.staging.secondary, get_staged_times(es, cfg, tables, bank_1, available_time)),
+        false => (primary, (bank_1, bank_2)),
+    };
+
+    // el tiempo muerto va en cada inyeccion
+    let dead_time = get_dead_time(es.sensors.batt, primary, tables.vbat_correction.as_ref());
+    es.injection.dead_time = dead_time;
+    let bank_2_dead_time = match cfg.injection.staging.enabled {
+        true => get_dead_time(es.sensors.batt, bank_2_injector, None),
+        false => dead_time,
+    };
+
+    let bank_1 = get_injection_pulse(bank_1, dead_time, primary);
+    let bank_2 = get_injection_pulse(bank_2, bank_2_dead_time, bank_2_injector);
+
+    // limite de ciclo de trabajo
+    let max_pulse_1 = available_time * primary.max_duty_cycle / 100.0;
+    let max_pulse_2 = available_time * bank_2_injector.max_duty_cycle / 100.0;
+
+    let overflow = bank_1 > max_pulse_1 || bank_2 > max_pulse_2;
+    es.diagnostics.set(DiagnosticCode::InjectorDutyOverflow, overflow);
+
+    es.injection.injection_bank_1_time = bank_1.min(max_pulse_1);
+    es.injection.injection_bank_2_time = bank_2.min(max_pulse_2);
+    es.injection.duty_cycle_1 = get_duty_cycle(es.injection.injection_bank_1_time, available_time);
+    es.injection.duty_cycle_2 = get_duty_cycle(es.injection.injection_bank_2_time, available_time);
+
+    // consumo
+    es.injection.fuel_flow_rate = get_fuel_flow_rate(es, cfg, get_injections_per_cycle(mode));
+    es.trip.update(es.injection.fuel_flow_rate, now);
+}
+
+/**
+ * @brief angulo de fin de inyeccion segun RPM / carga, si no hay tabla el fijo de la config
+ */
+pub fn get_eoi_angle(es: &EngineStatus, cfg: &EngineConfig, tables: &Tables) -> f32 {
+    match tables.eoi.as_ref() {
+        Some(eoi) => get_table_value(eoi, es.rpm as f32, get_fuel_load(es, cfg)),
+        None => cfg.injection.eoi_angle,
+    }
+}
+
+/**
+ * @brief modelo de combustible en uso, con la MAP en falla speed-density pasa a alpha-N si esta configurado
+ */
+pub fn get_fuel_model(es: &EngineStatus, cfg: &EngineConfig) -> Option<FuelModel> {
+    match cfg.injection.fuel_model() {
+        Some(FuelModel::SpeedDensity) if cfg.injection.map_fault_alpha_n && es.sensors.faults.is_active(SensorTypes::MAP) => {
+            Some(FuelModel::AlphaN)
+        }
+        model => model,
+    }
+}
+
+/**
+ * @brief carga con la que se indexa la VE (TPS en alpha-N, MAP o TPS en speed-density)
+ */
+pub fn get_fuel_load(es: &EngineStatus, cfg: &EngineConfig) -> f32 {
+    match (get_fuel_model(es, cfg), cfg.injection.ve_load_axis) {
+        (Some(FuelModel::SpeedDensity), VeLoadAxis::Map) => es.sensors.map,
+        _ => es.sensors.tps,
+    }
+}
+
+/**
+ * @brief completa aire y combustible base por cilindro (mg) y el caudal de aire (g/s)
+ */
+fn set_base_fuel(es: &mut EngineStatus, cfg: &EngineConfig, air_mass: f32, target_lambda: f32) {
+    let target_afr = es.injection.stoich * target_lambda;
+    // un ciclo cada dos vueltas
+    let cycles_per_second = es.rpm as f32 / 120.0;
+
+    es.injection.targetAFR = target_afr;
+    es.injection.base_air = air_mass;
+    es.injection.air_flow = air_mass * cfg.engine.cylinder_count as f32 * cycles_per_second / 1000.0;
+    // mismo resguardo que get_required_fuel
+    es.injection.base_fuel = if target_afr > 0.0 { air_mass / target_afr } else { 0.0 };
+}
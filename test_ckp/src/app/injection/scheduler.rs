@@ -0,0 +1,309 @@
+// Programacion de inyeccion por angulo:
+// - injection_checks calcula el tiempo de cada banco y se lo pasa al scheduler con update()
+// - en cada diente del CKP on_tooth() programa los canales cuyo inicio cae antes del proximo diente,
+//   el inicio sale del angulo de fin de inyeccion (EOI) menos el pulso pasado a grados
+// - TIM2 se arma con el evento mas cercano y on_timer() abre/cierra las salidas con timer4 como referencia
+//
+// Secuencial necesita saber en que vuelta del ciclo de 720° estamos, eso lo da el CMP.
+// Sin referencia de fase (todavia no hay decoder de CMP) se cae a semi-secuencial,
+// que inyecta cada 360° y no depende de la fase.
+
+use crate::app::{
+    engine::{
+        cpwm::{angle_to_time, time_to_angle, VRStatus},
+        efi_cfg::{EngineConfig, InjectionMode, MAX_CYLINDERS},
+        engine_status::InjectionInfo,
+    },
+    gpio::InjectionGpioMapping,
+};
+
+// salidas de inyeccion de la placa (iny_1 / iny_2)
+pub const INJECTION_OUTPUTS: usize = 2;
+
+// periodo del contador libre de TIM5 (timer4), en uS
+pub const TIMER_PERIOD: u32 = 1_000_000;
+
+// minimo que se le puede pedir a TIM2, en uS
+pub const MIN_TIMER_DELAY: u32 = 5;
+
+// margen para dar por vencido un evento
+const SCHEDULE_TOLERANCE: u32 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChannelState {
+    Idle,
+    Pending,
+    Open,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct InjectionChannel {
+    pub state: ChannelState,
+    // desfase respecto al cilindro 1, en grados
+    pub offset: f32,
+    // uS
+    pub pulse_width: u32,
+    // ticks de timer4
+    pub open_at: u32,
+    pub close_at: u32,
+}
+
+#[derive(Debug)]
+pub struct InjectionScheduler {
+    pub mode: InjectionMode,
+    pub channels: [InjectionChannel; INJECTION_OUTPUTS],
+    pub active_channels: usize,
+    pub cycle_angle: f32,
+    pub eoi_angle: f32,
+    // canal 0: primarios, canal 1: secundarios
+    pub staged: bool,
+}
+
+/**
+ * @brief modo de inyeccion que realmente se puede usar con las salidas de la placa,
+ * si no alcanzan las salidas o no hay referencia de fase para secuencial se cae a semi-secuencial
+ */
+pub fn effective_mode(cfg: &EngineConfig, has_phase: bool) -> InjectionMode {
+    // con inyeccion escalonada cada salida es un grupo, los dos inyectan en cada vuelta
+    // (documentado en StagingConfig.enabled, la config lo avisa al cargar)
+    if cfg.injection.staging.enabled {
+        return InjectionMode::Batch;
+    }
+
+    match cfg.injection.mode {
+        InjectionMode::Sequential if !has_phase => InjectionMode::SemiSequential,
+        InjectionMode::Sequential if cfg.engine.cylinder_count as usize > INJECTION_OUTPUTS => InjectionMode::SemiSequential,
+        mode => mode,
+    }
+}
+
+/**
+ * @brief canales en uso y angulo en que se repiten para el modo efectivo
+ */
+pub fn get_active_channels(mode: InjectionMode, cylinders: usize, staged: bool) -> (usize, f32) {
+    match mode {
+        InjectionMode::Batch if staged => (INJECTION_OUTPUTS, 360.0),
+        InjectionMode::Batch => (1, 360.0),
+        InjectionMode::SemiSequential => ((cylinders / 2).clamp(1, INJECTION_OUTPUTS), 360.0),
+        InjectionMode::Sequential => (cylinders, 720.0),
+    }
+}
+
+/**
+ * @brief entre cuantas salidas se reparten los cilindros para las correcciones,
+ * los dos grupos escalonados alimentan a todos los cilindros
+ */
+pub fn get_trim_outputs(active_channels: usize, staged: bool) -> usize {
+    if staged {
+        return 1;
+    }
+    active_channels
+}
+
+/**
+ * @brief correccion de una salida compartida, en orden de encendido la salida `output` maneja los
+ * cilindros output, output + outputs, ...; se aplica el promedio de esos cilindros
+ */
+pub fn get_output_trim(trims: &[f32; MAX_CYLINDERS], cylinders: usize, output: usize, outputs: usize) -> f32 {
+    let cylinders = cylinders.clamp(1, MAX_CYLINDERS);
+    let outputs = outputs.max(1);
+
+    let (sum, count) = trims[..cylinders]
+        .iter()
+        .skip(output % outputs)
+        .step_by(outputs)
+        .fold((0.0, 0), |(sum, count), trim| (sum + trim, count + 1));
+
+    sum / count.max(1) as f32
+}
+
+/**
+ * @brief false si algun par de cilindros que comparten salida tiene correcciones distintas,
+ * en ese caso solo se aplica el promedio
+ */
+pub fn is_output_trim_uniform(trims: &[f32; MAX_CYLINDERS], cylinders: usize, outputs: usize) -> bool {
+    let cylinders = cylinders.clamp(1, MAX_CYLINDERS);
+    let outputs = outputs.max(1);
+
+    (0..cylinders).all(|cylinder| trims[cylinder] == trims[cylinder % outputs])
+}
+
+/**
+ * @brief tiempo por inyeccion de cada banco a partir del tiempo total por ciclo
+ */
+pub fn get_bank_times(mode: InjectionMode, fuel_time: f32) -> (f32, f32) {
+    match mode {
+        // dos inyecciones por ciclo de 720°
+        InjectionMode::Batch | InjectionMode::SemiSequential => (fuel_time / 2.0, fuel_time / 2.0),
+        InjectionMode::Sequential => (fuel_time, fuel_time),
+    }
+}
+
+/**
+ * @brief cuantas veces inyecta cada canal por ciclo de 720°
+ */
+pub fn get_injections_per_cycle(mode: InjectionMode) -> u32 {
+    match mode {
+        InjectionMode::Batch | InjectionMode::SemiSequential => 2,
+        InjectionMode::Sequential => 1,
+    }
+}
+
+/**
+ * @brief aplica la correccion del cilindro (%) al pulso, sin tocar el tiempo muerto
+ */
+pub fn get_trimmed_pulse(pulse_width: f32, dead_time: f32, trim: f32) -> u32 {
+    if pulse_width <= dead_time {
+        return pulse_width as u32;
+    }
+
+    ((pulse_width - dead_time) * trim / 100.0 + dead_time) as u32
+}
+
+pub fn ticks_add(time: u32, delay: u32) -> u32 {
+    (time + delay) % TIMER_PERIOD
+}
+
+pub fn ticks_until(now: u32, time: u32) -> u32 {
+    (time + TIMER_PERIOD - now) % TIMER_PERIOD
+}
+
+pub fn is_due(now: u32, time: u32) -> bool {
+    let remaining = ticks_until(now, time);
+    // si falta "mas de medio periodo" es porque ya paso
+    remaining <= SCHEDULE_TOLERANCE || remaining > TIMER_PERIOD / 2
+}
+
+pub use open_efi::math::ignition::wrap_angle;
+
+impl InjectionChannel {
+    pub fn new() -> InjectionChannel {
+        InjectionChannel {
+            state: ChannelState::Idle,
+            offset: 0.0,
+            pulse_width: 0,
+            open_at: 0,
+            close_at: 0,
+        }
+    }
+}
+
+impl InjectionScheduler {
+    pub fn new() -> InjectionScheduler {
+        InjectionScheduler {
+            mode: InjectionMode::Batch,
+            channels: [InjectionChannel::new(); INJECTION_OUTPUTS],
+            active_channels: 1,
+            cycle_angle: 360.0,
+            eoi_angle: 0.0,
+            staged: false,
+        }
+    }
+
+    /**
+     * @brief toma el modo y los tiempos calculados para cada banco,
+     * los canales que ya estan programados terminan con el tiempo anterior
+     */
+    pub fn update(&mut self, cfg: &EngineConfig, injection: &InjectionInfo, has_phase: bool) {
+        let mode = effective_mode(cfg, has_phase);
+        let cylinders = (cfg.engine.cylinder_count as usize).max(1);
+
+        let staged = cfg.injection.staging.enabled;
+
+        let (active_channels, cycle_angle) = get_active_channels(mode, cylinders, staged);
+        let trim_outputs = get_trim_outputs(active_channels, staged);
+
+        self.mode = mode;
+        self.staged = staged;
+        self.active_channels = active_channels;
+        self.cycle_angle = cycle_angle;
+        self.eoi_angle = injection.eoi_angle;
+
+        for (i, channel) in self.channels.iter_mut().enumerate() {
+            // los dos grupos escalonados inyectan juntos
+            channel.offset = if staged { 0.0 } else { i as f32 * cycle_angle / active_channels as f32 };
+
+            let pulse_width = match i {
+                0 => injection.injection_bank_1_time,
+                _ => injection.injection_bank_2_time,
+            };
+
+            // en secuencial cada canal es un cilindro, si no el promedio de los que maneja
+            let trim = get_output_trim(&cfg.engine.cylinder_trim.fuel, cylinders, i, trim_outputs);
+            channel.pulse_width = get_trimmed_pulse(pulse_width, injection.dead_time, trim);
+        }
+    }
+
+    /**
+     * @brief se llama en cada diente del CKP, programa los canales cuyo inicio cae antes del proximo diente
+     */
+    pub fn on_tooth(&mut self, trigger: &VRStatus, crank_angle: f32, tooth_angle: f32, now: u32) {
+        let eoi_angle = self.eoi_angle;
+        let cycle_angle = self.cycle_angle;
+
+        for channel in self.channels[..self.active_channels].iter_mut() {
+            if channel.state != ChannelState::Idle || channel.pulse_width == 0 {
+                continue;
+            }
+
+            let pulse_angle = time_to_angle(trigger, &channel.pulse_width) as f32;
+            let start_angle = wrap_angle(eoi_angle + channel.offset - pulse_angle, cycle_angle);
+            let angle_to_start = wrap_angle(start_angle - crank_angle, cycle_angle);
+
+            // x2 para no perder el evento en el hueco del diente faltante
+            if angle_to_start < tooth_angle * 2.0 {
+                let delay = angle_to_time(trigger, &(angle_to_start as u32)).max(0) as u32;
+
+                channel.open_at = ticks_add(now, delay);
+                channel.close_at = ticks_add(channel.open_at, channel.pulse_width);
+                channel.state = ChannelState::Pending;
+            }
+        }
+    }
+
+    /**
+     * @brief se llama desde la interrupcion de TIM2, abre/cierra los inyectores que ya vencieron
+     */
+    pub fn on_timer(&mut self, now: u32, pins: &mut InjectionGpioMapping) {
+        for i in 0..self.active_channels {
+            let channel = self.channels[i];
+
+            if channel.state == ChannelState::Pending && is_due(now, channel.open_at) {
+                self.set_output(i, true, pins);
+                self.channels[i].state = ChannelState::Open;
+            }
+
+            if self.channels[i].state == ChannelState::Open && is_due(now, channel.close_at) {
+                self.set_output(i, false, pins);
+                self.channels[i].state = ChannelState::Idle;
+            }
+        }
+    }
+
+    /**
+     * @brief uS hasta el proximo evento programado, None si no hay nada pendiente
+     */
+    pub fn next_event(&self, now: u32) -> Option<u32> {
+        self.channels[..self.active_channels]
+            .iter()
+            .filter_map(|channel| match channel.state {
+                ChannelState::Pending => Some(ticks_until(now, channel.open_at)),
+                ChannelState::Open => Some(ticks_until(now, channel.close_at)),
+                ChannelState::Idle => None,
+            })
+            .map(|delay| if delay > TIMER_PERIOD / 2 { MIN_TIMER_DELAY } else { delay.max(MIN_TIMER_DELAY) })
+            .min()
+    }
+
+    fn set_output(&self, channel: usize, open: bool, pins: &mut InjectionGpioMapping) {
+        match (self.mode, channel) {
+            (InjectionMode::Batch, _) if !self.staged => {
+                pins.iny_1.set_state(open.into());
+                pins.iny_2.set_state(open.into());
+            }
+            (_, 0) => pins.iny_1.set_state(open.into()),
+            (_, 1) => pins.iny_2.set_state(open.into()),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::app::{
+    engine::{
+        efi_cfg::{EngineConfig, VeLoadAxis},
+        engine_status::EngineStatus,
+    },
+    injection::air_density::get_iat_trim,
+    memory::tables::{get_table_value, Tables},
+};
+
+pub use open_efi::math::speed_density::{get_air_density, get_air_mass, KELVIN, R_AIR};
+
+pub fn get_ve(es: &EngineStatus, cfg: &EngineConfig, tables: &Tables) -> Option<f32> {
+    let load = match cfg.injection.ve_load_axis {
+        VeLoadAxis::Map => es.sensors.map,
+        VeLoadAxis::Tps => es.sensors.tps,
+    };
+
+    tables.tps_rpm_ve.as_ref().map(|ve| get_table_value(ve, es.rpm as f32, load))
+}
+
+/**
+ * @brief masa de aire (mg) por speed-density, None si no esta cargada la tabla de VE,
+ * la MAP es absoluta asi que no lleva correccion por baro y la densidad usa siempre la IAT medida
+ */
+pub fn calculate_air_mass(es: &EngineStatus, cfg: &EngineConfig, tables: &Tables) -> Option<f32> {
+    let ve = get_ve(es, cfg, tables)?;
+
+    let air_mass = get_air_mass(
+        cfg.engine.displacement,
+        cfg.engine.cylinder_count,
+        ve,
+        es.sensors.map,
+        es.sensors.air_temp,
+    );
+
+    Some(air_mass * get_iat_trim(es.sensors.air_temp, &cfg.injection.air_density) / 100.0)
+}
@@ -0,0 +1,35 @@
+use crate::app::{
+    engine::{
+        efi_cfg::{EngineConfig, StagingMode},
+        engine_status::EngineStatus,
+    },
+    injection::{get_fuel_load, injectors::convert_pulse},
+    memory::tables::{get_table_value, Tables},
+};
+
+/**
+ * @brief reparte el pulso de una inyeccion (uS, calculado para los primarios) entre primarios y secundarios,
+ * devuelve el tiempo efectivo de cada grupo sin tiempo muerto
+ */
+pub fn get_staged_times(es: &mut EngineStatus, cfg: &EngineConfig, tables: &Tables, pulse: f32, available_time: f32) -> (f32, f32) {
+    let staging = &cfg.injection.staging;
+
+    // % del combustible que va a los secundarios
+    let secondary_share = match staging.mode {
+        StagingMode::Table => tables
+            .staging
+            .as_ref()
+            .map_or(0.0, |table| get_table_value(table, es.rpm as f32, get_fuel_load(es, cfg)).clamp(0.0, 100.0)),
+        // lo que no entra en los primarios hasta duty_threshold
+        StagingMode::DutyCycle => {
+            let max_primary = available_time * staging.duty_threshold / 100.0;
+            if pulse > max_primary && pulse > 0.0 { (pulse - max_primary) / pulse * 100.0 } else { 0.0 }
+        }
+    };
+    es.injection.staging_share = secondary_share;
+
+    let primary = pulse * (100.0 - secondary_share) / 100.0;
+    let secondary = convert_pulse(pulse * secondary_share / 100.0, &cfg.injection.injector, &staging.secondary);
+
+    (primary, secondary)
+}
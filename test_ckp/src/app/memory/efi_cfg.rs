@@ -1,19 +1,25 @@
-use postcard::{from_bytes, to_vec};
-use serde_json_core::heapless::Vec;
+use postcard::{experimental::max_size::MaxSize, from_bytes, to_slice};
 use stm32f4xx_hal::crc32::Crc32;
 use w25q::series25::FlashInfo;
 
-use crate::app::engine::efi_cfg::{EngineConfig, get_default_efi_cfg};
+use crate::app::engine::efi_cfg::{EngineConfig, get_default_efi_cfg, InjectionMode};
 use crate::app::logging::host;
 use crate::app::memory::tables::FlashT;
 
 const ENGINE_CONFIG_MEMORY_ADDRESS: u32 = 0;
+// tamaño maximo de la config serializada (todas las curvas cargadas)
+const ENGINE_CONFIG_SIZE: usize = EngineConfig::POSTCARD_MAX_SIZE;
+// los datos van en un solo sector de 4KB
+const _: () = assert!(ENGINE_CONFIG_SIZE <= 4096);
 
 impl EngineConfig {
-    pub fn save(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
-        //TODO: ajustar tamaño del vector
+    /**
+     * @brief graba la config, el buffer completo (con el resto en 0) es lo que cubre el CRC
+     */
+    pub fn save(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) -> Result<(), postcard::Error> {
         host::debug!("Guardando cfg");
-        let mut output: Vec<u8, 800> = to_vec(&self).unwrap();
+        let mut output: [u8; ENGINE_CONFIG_SIZE] = [0; ENGINE_CONFIG_SIZE];
+        to_slice(&self, &mut output)?;
 
         crc.init();
         let calculated_crc = crc.update_bytes(&output);
@@ -23,15 +29,18 @@ impl EngineConfig {
         let crc_addr = flash_info.sector_to_page(&ENGINE_CONFIG_MEMORY_ADDRESS) * (flash_info.page_size as u32);
 
         {
-            flash.erase_sectors(ENGINE_CONFIG_MEMORY_ADDRESS, 1).unwrap();
+            flash.erase_sectors(crc_addr, 1).unwrap();
+            flash.erase_sectors(write_address, 1).unwrap();
             flash.write_bytes(crc_addr, &mut crc_arr).unwrap();
             flash.write_bytes(write_address, &mut output).unwrap();
         }
+
+        Ok(())
     }
     pub fn read(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
         // TODO: tirar error en caso de que la palme el crc
 
-        let mut read_buff: [u8; 800] = [0; 800];
+        let mut read_buff: [u8; ENGINE_CONFIG_SIZE] = [0; ENGINE_CONFIG_SIZE];
         let mut crc_buff: [u8; 4] = [0; 4];
         let read_address = flash_info.sector_to_page(&(ENGINE_CONFIG_MEMORY_ADDRESS + 4)) * (flash_info.page_size as u32);
         let crc_addr = flash_info.sector_to_page(&ENGINE_CONFIG_MEMORY_ADDRESS) * (flash_info.page_size as u32);
@@ -59,10 +68,27 @@ impl EngineConfig {
         }
 
         {
-            let mut memory_config: EngineConfig = from_bytes(&read_buff).unwrap();
+            // si cambio la estructura de la config queda la de por defecto
+            let mut memory_config: EngineConfig = match from_bytes(&read_buff) {
+                Ok(config) => config,
+                Err(_) => {
+                    host::debug!("Config en memoria no compatible");
+                    return;
+                }
+            };
+
+            if memory_config.injection.fuel_model().is_none() {
+                host::debug!("Config invalida: alpha-N y speed-density a la vez (o ninguno)");
+                return;
+            }
+
+            if memory_config.injection.staging.enabled && memory_config.injection.mode != InjectionMode::Batch {
+                host::debug!("Inyeccion escalonada: se ignora el modo de inyeccion, corre en batch");
+            }
 
             self.injection = memory_config.injection.clone();
             self.engine = memory_config.engine.clone();
+            self.ignition = memory_config.ignition.clone();
             self.ready = true;
         }
     }
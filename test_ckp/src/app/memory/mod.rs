@@ -1,2 +1,5 @@
 pub mod tables;
-pub mod efi_cfg;
\ No newline at end of file
+pub mod efi_cfg;
+pub mod rotating;
+pub mod sensor_cfg;
+pub mod trip;
@@ -0,0 +1,68 @@
+use stm32f4xx_hal::crc32::Crc32;
+use w25q::series25::FlashInfo;
+
+use crate::app::memory::tables::FlashT;
+
+// cada copia arranca con crc (4) + secuencia (4), el crc cubre la secuencia y los datos
+const HEADER_SIZE: u32 = 8;
+
+fn sector_address(sector: u32, flash_info: &FlashInfo) -> u32 {
+    flash_info.sector_to_page(&sector) * (flash_info.page_size as u32)
+}
+
+fn get_crc(sequence: u32, data: &[u8], crc: &mut Crc32) -> u32 {
+    crc.init();
+    crc.update_bytes(&u32::to_le_bytes(sequence));
+    crc.update_bytes(data)
+}
+
+/**
+ * @brief datos que se graban seguido (LTFT, consumo), cada grabacion va al siguiente sector
+ * de `sectors` para repartir los borrados; la copia valida con la secuencia mas alta es la actual.
+ * Devuelve la secuencia leida (None si no hay ninguna copia valida) y deja los datos en `data`
+ */
+pub fn read_rotating(sectors: &[u32], data: &mut [u8], flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) -> Option<u32> {
+    let mut newest: Option<(u32, u32)> = None;
+
+    for sector in sectors {
+        let address = sector_address(*sector, flash_info);
+        let mut header = [0u8; HEADER_SIZE as usize];
+
+        flash.read(address, &mut header).unwrap();
+        flash.read(address + HEADER_SIZE, data).unwrap();
+
+        let memory_crc = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let sequence = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+        if memory_crc != get_crc(sequence, data, crc) {
+            continue;
+        }
+
+        if newest.map_or(true, |(newest_sequence, _)| sequence > newest_sequence) {
+            newest = Some((sequence, *sector));
+        }
+    }
+
+    let (sequence, sector) = newest?;
+    flash.read(sector_address(sector, flash_info) + HEADER_SIZE, data).unwrap();
+
+    Some(sequence)
+}
+
+/**
+ * @brief graba `data` como la copia `sequence`, siempre una mas que la ultima leida/grabada
+ */
+pub fn write_rotating(sectors: &[u32], sequence: u32, data: &mut [u8], flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
+    let sector = sectors[sequence as usize % sectors.len()];
+    let address = sector_address(sector, flash_info);
+
+    let mut header = [0u8; HEADER_SIZE as usize];
+    header[..4].copy_from_slice(&u32::to_le_bytes(get_crc(sequence, data, crc)));
+    header[4..].copy_from_slice(&u32::to_le_bytes(sequence));
+
+    {
+        flash.erase_sectors(address, 1).unwrap();
+        flash.write_bytes(address, &mut header).unwrap();
+        flash.write_bytes(address + HEADER_SIZE, data).unwrap();
+    }
+}
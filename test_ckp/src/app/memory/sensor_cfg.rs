@@ -0,0 +1,78 @@
+use postcard::{from_bytes, to_vec};
+use serde_json_core::heapless::Vec;
+use stm32f4xx_hal::crc32::Crc32;
+use w25q::series25::FlashInfo;
+
+use crate::app::engine::sensor_cfg::SensorConfig;
+use crate::app::logging::host;
+use crate::app::memory::tables::{FlashT, SENSOR_CONFIG_SECTOR};
+
+// tamaño maximo de la calibracion serializada
+const SENSOR_CONFIG_SIZE: usize = 2048;
+
+impl SensorConfig {
+    pub fn save(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
+        host::debug!("Guardando calibracion de sensores");
+        let output: Vec<u8, SENSOR_CONFIG_SIZE> = to_vec(&self).unwrap();
+
+        crc.init();
+        let calculated_crc = crc.update_bytes(&output);
+        let mut buf: [u8; SENSOR_CONFIG_SIZE + 4] = [0; SENSOR_CONFIG_SIZE + 4];
+        buf[..4].copy_from_slice(&u32::to_le_bytes(calculated_crc));
+        buf[4..4 + output.len()].copy_from_slice(&output);
+
+        let write_address = flash_info.sector_to_page(&SENSOR_CONFIG_SECTOR) * (flash_info.page_size as u32);
+
+        {
+            flash.erase_sectors(write_address, 1).unwrap();
+            flash.write_bytes(write_address, &mut buf).unwrap();
+        }
+    }
+
+    /**
+     * @brief si la flash no tiene una calibracion valida queda la de por defecto
+     */
+    pub fn read(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
+        let mut buf: [u8; SENSOR_CONFIG_SIZE + 4] = [0; SENSOR_CONFIG_SIZE + 4];
+        let read_address = flash_info.sector_to_page(&SENSOR_CONFIG_SECTOR) * (flash_info.page_size as u32);
+
+        {
+            flash.read(read_address, &mut buf).unwrap();
+        }
+
+        let memory_crc = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+        let memory_config: SensorConfig = match from_bytes(&buf[4..]) {
+            Ok(config) => config,
+            Err(_) => {
+                host::debug!("Calibracion de sensores en memoria no compatible");
+                return;
+            }
+        };
+
+        // el CRC es solo de los bytes serializados
+        let serialized: Vec<u8, SENSOR_CONFIG_SIZE> = to_vec(&memory_config).unwrap();
+        crc.init();
+        let calculated_crc = crc.update_bytes(&serialized);
+
+        if memory_crc != calculated_crc {
+            host::debug!("Checksum calibracion de sensores no coincide {:?}  {:?}", memory_crc, calculated_crc);
+            return;
+        }
+
+        *self = memory_config;
+
+        // un alpha fuera de 0 - 1 hace diverger la EMA, se corrige y queda avisado
+        let mut filters_changed = self.filters.validate();
+        for channel in self.aux.channels.iter_mut() {
+            filters_changed |= channel.filter.validate();
+        }
+        if filters_changed {
+            host::debug!("Filtros de sensores fuera de rango, se corrigieron");
+        }
+
+        self.clt.update_coefficients();
+        self.iat.update_coefficients();
+        self.ready = true;
+    }
+}
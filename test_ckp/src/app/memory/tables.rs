@@ -10,21 +10,55 @@ use stm32f4xx_hal::{
 use w25q::series25::{Flash, FlashInfo};
 
 use crate::app::logging::host;
-
-pub type DataT = [[i32; 17]; 17];
-pub type PlotData = [[i32; 2]; 10];
+use crate::app::memory::rotating::{read_rotating, write_rotating};
+
+pub use open_efi::math::tables::{get_nearest_cell, get_plot_value, get_table_value, DataT, PlotData, TABLE_SCALE, TABLE_SIZE};
+
+// mapa de sectores (4KB) de la flash:
+//   0 - 4    config del motor (ver memory/efi_cfg.rs)
+//   8        calibracion de sensores (ver memory/sensor_cfg.rs)
+//   16 - 31  tablas, un sector cada una
+//   32 - 35  LTFT, rota entre los cuatro (ver memory/rotating.rs)
+//   36 - 39  consumo de combustible, rota entre los cuatro (ver memory/trip.rs)
+pub const SENSOR_CONFIG_SECTOR: u32 = 8;
+
+pub const TPS_RPM_VE_SECTOR: u32 = 16;
+pub const TPS_RPM_AFR_SECTOR: u32 = 17;
+pub const VBAT_CORRECTION_SECTOR: u32 = 18;
+pub const WUE_SECTOR: u32 = 19;
+pub const ASE_TAPER_SECTOR: u32 = 20;
+pub const ASE_INTENSITY_SECTOR: u32 = 21;
+pub const LOAD_TPS_DEG_SECTOR: u32 = 22;
+pub const ETHANOL_ADVANCE_SECTOR: u32 = 23;
+pub const STAGING_SECTOR: u32 = 24;
+pub const EOI_SECTOR: u32 = 25;
+
+pub const LTFT_SECTORS: [u32; 4] = [32, 33, 34, 35];
+pub const TRIP_SECTORS: [u32; 4] = [36, 37, 38, 39];
 
 pub struct Tables {
     // injection
     pub tps_rpm_ve: Option<DataT>,
     pub tps_rpm_afr: Option<DataT>,
     pub injector_delay: Option<DataT>,
+    // x: mV de bateria, y: tiempo muerto del inyector en uS
     pub vbat_correction: Option<PlotData>,
+    // x: °C, y: % de combustible (100 = sin correccion)
     pub wue: Option<PlotData>,
+    // x: °C, y: ciclos hasta terminar el ASE
     pub ase_taper: Option<PlotData>,
+    // x: °C, y: % agregado al arrancar
     pub ase_intensity: Option<PlotData>,
+    // mismos ejes que la VE, valores: % de correccion aprendida
+    pub ltft: Option<DataT>,
+    // mismos ejes que la VE, valores: % del combustible a los secundarios
+    pub staging: Option<DataT>,
+    // mismos ejes que la VE, valores: angulo de fin de inyeccion
+    pub eoi: Option<DataT>,
     //ignition
     pub load_tps_deg: Option<DataT>,
+    // mismos ejes que load_tps_deg, avance con E100
+    pub ethanol_advance: Option<DataT>,
 }
 
 pub struct TableData {
@@ -49,6 +83,16 @@ pub type SpiT = Spi<
 pub type FlashT = Flash<SharedBus<SpiT>, Pin<'E', 13, Output>>;
 
 impl TableData {
+    pub fn new(address: u32, max_x: u16, max_y: u16) -> TableData {
+        TableData {
+            data: None,
+            crc: 0,
+            address,
+            max_x,
+            max_y,
+        }
+    }
+
     pub fn read_from_memory(
         &mut self,
         flash: &mut FlashT,
@@ -86,7 +130,8 @@ impl TableData {
         let calculated_crc = crc.update_bytes(&buf[4..]);
 
         if memory_crc != calculated_crc {
-            host::debug!("Checksum tablas no coinciden {:?}  {:?}", memory_crc,calculated_crc)
+            host::debug!("Checksum tablas no coinciden {:?}  {:?}", memory_crc,calculated_crc);
+            return None;
         }
 
 
@@ -186,3 +231,59 @@ impl TableData {
         return true;
     }
 }
+
+pub fn read_table(address: u32, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) -> Option<DataT> {
+    let mut table = TableData::new(address, 17, 17);
+    table.read_from_memory(flash, fi, crc)
+}
+
+pub fn write_table(address: u32, data: &DataT, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) {
+    let mut table = TableData::new(address, 17, 17);
+    table.data = Some(*data);
+    table.write_to_memory(flash, fi, crc);
+}
+
+// tabla sin CRC, la usan los datos que rotan entre sectores (LTFT)
+pub const TABLE_BYTES: usize = 4 * TABLE_SIZE * TABLE_SIZE;
+
+pub fn table_to_bytes(data: &DataT) -> [u8; TABLE_BYTES] {
+    let mut buf = [0u8; TABLE_BYTES];
+    for (chunk, value) in buf.chunks_exact_mut(4).zip(data.iter().flatten()) {
+        chunk.copy_from_slice(&i32::to_le_bytes(*value));
+    }
+    buf
+}
+
+pub fn table_from_bytes(buf: &[u8; TABLE_BYTES]) -> DataT {
+    let mut data = [[0i32; TABLE_SIZE]; TABLE_SIZE];
+    for (value, chunk) in data.iter_mut().flatten().zip(buf.chunks_exact(4)) {
+        *value = i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    data
+}
+
+/**
+ * @brief ultima copia de la LTFT y su secuencia, None si no hay ninguna valida
+ */
+pub fn read_ltft(flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) -> Option<(DataT, u32)> {
+    let mut buf = [0u8; TABLE_BYTES];
+    let sequence = read_rotating(&LTFT_SECTORS, &mut buf, flash, fi, crc)?;
+    Some((table_from_bytes(&buf), sequence))
+}
+
+pub fn write_ltft(data: &DataT, sequence: u32, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) {
+    write_rotating(&LTFT_SECTORS, sequence, &mut table_to_bytes(data), flash, fi, crc);
+}
+
+// las curvas se guardan igual que las tablas, en las primeras 2 columnas
+pub fn read_plot(address: u32, flash: &mut FlashT, fi: &FlashInfo, crc: &mut Crc32) -> Option<PlotData> {
+    let mut table = TableData::new(address, 2, 10);
+    let data = table.read_from_memory(flash, fi, crc)?;
+
+    let mut plot: PlotData = [[0; 2]; 10];
+    for (i, point) in plot.iter_mut().enumerate() {
+        *point = [data[i][0], data[i][1]];
+    }
+
+    Some(plot)
+}
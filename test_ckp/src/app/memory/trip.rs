@@ -0,0 +1,51 @@
+use postcard::{experimental::max_size::MaxSize, from_bytes, to_slice};
+use stm32f4xx_hal::crc32::Crc32;
+use w25q::series25::FlashInfo;
+
+use crate::app::injection::fuel_flow::TripComputer;
+use crate::app::logging::host;
+use crate::app::memory::rotating::{read_rotating, write_rotating};
+use crate::app::memory::tables::{FlashT, TRIP_SECTORS};
+
+// tamaño maximo del consumo serializado
+const TRIP_SIZE: usize = TripComputer::POSTCARD_MAX_SIZE;
+
+impl TripComputer {
+    /**
+     * @brief graba el consumo como la copia `sequence` (ver memory/rotating.rs)
+     */
+    pub fn save(&self, sequence: u32, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) -> Result<(), postcard::Error> {
+        let mut buf: [u8; TRIP_SIZE] = [0; TRIP_SIZE];
+        to_slice(self, &mut buf)?;
+
+        write_rotating(&TRIP_SECTORS, sequence, &mut buf, flash, flash_info, crc);
+        Ok(())
+    }
+
+    /**
+     * @brief si la flash no tiene un consumo valido queda en cero
+     */
+    pub fn read(&mut self, flash: &mut FlashT, flash_info: &FlashInfo, crc: &mut Crc32) {
+        let mut buf: [u8; TRIP_SIZE] = [0; TRIP_SIZE];
+
+        let sequence = match read_rotating(&TRIP_SECTORS, &mut buf, flash, flash_info, crc) {
+            Some(sequence) => sequence,
+            None => {
+                host::debug!("Consumo en memoria no disponible");
+                return;
+            }
+        };
+
+        let trip: TripComputer = match from_bytes(&buf) {
+            Ok(trip) => trip,
+            Err(_) => {
+                host::debug!("Consumo en memoria no compatible");
+                return;
+            }
+        };
+
+        self.trip_fuel = trip.trip_fuel;
+        self.total_fuel = trip.total_fuel;
+        self.sequence = sequence;
+    }
+}
@@ -2,13 +2,17 @@
 use rtic::Mutex;
 use rtic::mutex_prelude::{TupleExt03, TupleExt04};
 use stm32f4xx_hal::gpio::ExtiPin;
+use stm32f4xx_hal::pac::TIM10;
 use rtic_monotonics::systick::*;
+use rtic_monotonics::Monotonic;
 use crate::{
     app,
 };
-use crate::app::engine::cpwm::{angle_to_time, get_crank_angle};
+use crate::app::engine::cpwm::{angle_to_time, get_crank_angle, get_cranking_rpm};
 
 use crate::app::engine::efi_cfg::VRSensor;
+use crate::app::engine::engine_status::{EngineStatus, __rpm_status};
+use crate::app::engine::get_rpm_status;
 
 // from: https://github.com/noisymime/speeduino/blob/master/speeduino/decoders.ino#L453
 pub(crate) fn ckp_trigger(mut ctx: app::ckp_trigger::Context) {
@@ -106,6 +110,42 @@ pub(crate) fn ckp_trigger(mut ctx: app::ckp_trigger::Context) {
             }
         }
     });
+
+    // programacion de inyeccion
+    (&mut ckp_status, ctx.shared.inj_scheduler, ctx.shared.timer).lock(|ckp_status, scheduler, timer| {
+        if !ckp_status.has_sync {
+            return;
+        }
+
+        let mut crank_angle = get_crank_angle(ckp_status, &ckp, ckp_status.current_time) as f32;
+
+        // TODO: falta el decoder de CMP, cuando marque has_phase tiene que alinear start_revolution
+        // para que las vueltas impares sean la segunda mitad del ciclo; sin fase el scheduler nunca usa 720°
+        if scheduler.cycle_angle > 360.0 && ckp_status.has_phase && ckp_status.start_revolution % 2 == 1 {
+            crank_angle += 360.0;
+        }
+
+        scheduler.on_tooth(ckp_status, crank_angle, ckp.trigger_tooth_angle, ckp_status.current_time);
+
+        if let Some(delay) = scheduler.next_event(ckp_status.current_time) {
+            timer.start(delay.micros()).ok();
+        }
+    });
+
+    // programacion del encendido, chispa perdida cada 360°
+    (ckp_status, ctx.shared.ign_scheduler, ctx.shared.timer3).lock(|ckp_status, scheduler, timer| {
+        if !ckp_status.has_sync {
+            return;
+        }
+
+        let crank_angle = get_crank_angle(ckp_status, &ckp, ckp_status.current_time) as f32;
+        scheduler.on_tooth(ckp_status, crank_angle, ckp.trigger_tooth_angle, ckp_status.current_time);
+
+        if let Some(delay) = scheduler.next_event(ckp_status.current_time) {
+            timer.start(delay.micros()).ok();
+        }
+    });
+
     // Obtain access to the peripheral and Clear Interrupt Pending Flag
     ctx.local.ckp.clear_interrupt_pending_bit();
 }
@@ -135,6 +175,13 @@ pub(crate) async fn ckp_checks(mut ctx:  app::ckp_checks::Context<'_>) {
                 // tambien utilizan rpmDOT para ver la variacion cada 100mS, falta implementar
                 // TODO: mover a fun aparte
 
+                efi_status.rpm = get_cranking_rpm(ckp, &cfg.engine.ckp) as i32;
+                // sin sync las RPM dan 0 pero el motor sigue girando,
+                // el estado se mantiene hasta recuperar el sync o detectar el stall
+                if ckp.has_sync {
+                    update_cycle_status(efi_status, ckp.start_revolution);
+                }
+
                 let mut time_per_degreex16;
 
                 // esto calcula el tiempo por grado desde el tiempo entre los ultimos 2 dientes
@@ -145,7 +192,7 @@ pub(crate) async fn ckp_checks(mut ctx:  app::ckp_checks::Context<'_>) {
                     //Take into account any likely acceleration that has occurred since the last full revolution completed:
                     //long rpm_adjust = (timeThisRevolution * (long)currentStatus.rpmDOT) / 1000000;
                     let rpm_adjust = 0;
-                    time_per_degreex16 = (2_666_656 / efi_status.rpm + rpm_adjust) as u32; //The use of a x16 value gives accuracy down to 0.1 of a degree and can provide noticeably better timing results on low resolution triggers
+                    time_per_degreex16 = (2_666_656 / efi_status.rpm.max(1) + rpm_adjust) as u32; //The use of a x16 value gives accuracy down to 0.1 of a degree and can provide noticeably better timing results on low resolution triggers
                     // timePerDegree = time_per_degreex16 / 16;
                 }
 
@@ -153,10 +200,65 @@ pub(crate) async fn ckp_checks(mut ctx:  app::ckp_checks::Context<'_>) {
                 ckp.degreesPeruSx32768 = (524288 / time_per_degreex16) as f32;
             } else {
                 ckp.reset();
+                efi_status.rpm = 0;
+                update_cycle_status(efi_status, 0);
             }
+            efi_status.has_phase = ckp.has_phase;
             cfg.engine.ckp.max_stall_time;
         });
 
         Systick::delay(100.micros()).await;
         app::ckp_checks::spawn().unwrap();
-}
\ No newline at end of file
+}
+
+/**
+ * @brief como en speeduino, RUNNING queda enganchado hasta que el motor se para (stall o RPM < 50),
+ * una caida de RPM en ralenti no lo vuelve a arranque ni reinicia los ciclos
+ */
+fn update_cycle_status(efi_status: &mut EngineStatus, revolution: u128) {
+    let status = match (efi_status.cycle_status, get_rpm_status(efi_status.rpm)) {
+        (_, __rpm_status::STOPPED) => __rpm_status::STOPPED,
+        (__rpm_status::RUNNING, _) => __rpm_status::RUNNING,
+        (_, status) => status,
+    };
+
+    if status == __rpm_status::RUNNING {
+        if efi_status.cycle_status != __rpm_status::RUNNING {
+            efi_status.running_revolutions = 0;
+        } else {
+            // start_revolution vuelve a 0 si se pierde el sync, se suman solo las vueltas nuevas
+            let revolutions = revolution.saturating_sub(efi_status.last_revolution) as u32;
+            efi_status.running_revolutions = efi_status.running_revolutions.saturating_add(revolutions);
+        }
+        // dos vueltas por ciclo
+        efi_status.cycle_tick = efi_status.running_revolutions / 2;
+    } else {
+        efi_status.running_revolutions = 0;
+        efi_status.cycle_tick = 0;
+    }
+
+    efi_status.last_revolution = revolution;
+    efi_status.cycle_status = status;
+}
+
+/**
+ * @brief captura de TIM10 CH1 del sensor de etanol (aux in_7), la polaridad se alterna en cada captura
+ * para medir periodo y ancho del pulso por hardware
+ */
+pub(crate) fn flex_trigger(mut ctx: app::flex_trigger::Context) {
+    let now_ms = Systick::now().duration_since_epoch().to_millis();
+
+    // TIM10 lo configura init (ver flex_timer), aca solo se leen los registros de captura
+    let tim = unsafe { &*TIM10::ptr() };
+
+    if tim.sr.read().cc1if().bit_is_clear() {
+        return;
+    }
+
+    // leer CCR1 limpia CC1IF, con CC1P en 0 se capturo un flanco de subida
+    let captured = tim.ccr1().read().bits();
+    let rising = tim.ccer.read().cc1p().bit_is_clear();
+    tim.ccer.modify(|_, w| w.cc1p().bit(rising));
+
+    ctx.shared.flex.lock(|flex| flex.on_edge(rising, captured, now_ms));
+}
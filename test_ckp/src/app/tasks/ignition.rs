@@ -0,0 +1,22 @@
+use rtic::Mutex;
+use rtic::mutex_prelude::TupleExt03;
+use rtic_monotonics::systick::*;
+use stm32f4xx_hal::timer::Event;
+
+use crate::app;
+
+pub(crate) fn ignition_trigger(mut ctx: app::ignition_trigger::Context) {
+    let mut now = 0;
+    ctx.shared.timer4.lock(|t4| { now = t4.now().ticks(); });
+
+    (ctx.shared.timer3, ctx.shared.ign_scheduler, ctx.shared.ign_pins).lock(|timer, scheduler, pins| {
+        timer.clear_interrupt(Event::Update);
+
+        scheduler.on_timer(now, pins);
+
+        match scheduler.next_event(now) {
+            Some(delay) => { timer.start(delay.micros()).ok(); }
+            None => { timer.cancel().ok(); }
+        }
+    });
+}
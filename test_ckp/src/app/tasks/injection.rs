@@ -0,0 +1,149 @@
+use rtic::Mutex;
+use rtic::mutex_prelude::{TupleExt02, TupleExt03};
+use rtic_monotonics::systick::*;
+use rtic_monotonics::Monotonic;
+use stm32f4xx_hal::timer::Event;
+
+use crate::app;
+use crate::app::engine::{diagnostics::DiagnosticCode, engine_status::__rpm_status};
+use crate::app::ignition::calculate_advance;
+use crate::app::injection::{air_density::update_baro, calculate_time_isr, cranking::get_priming_pulse};
+use crate::app::logging::host;
+use crate::app::memory::tables::write_ltft;
+
+// S entre grabaciones del consumo con el motor en marcha, al pararse se graba siempre
+const TRIP_SAVE_INTERVAL: u32 = 1800;
+// mS entre revisiones de si hay que grabar la LTFT / el consumo
+const SAVE_CHECK_INTERVAL: u32 = 1000;
+
+pub(crate) async fn injection_checks(ctx: app::injection_checks::Context<'_>) {
+    let mut sensor_values = ctx.shared.sensors;
+    let mut flex_sensor = ctx.shared.flex;
+    let mut inj_scheduler = ctx.shared.inj_scheduler;
+    let mut ign_scheduler = ctx.shared.ign_scheduler;
+    let mut inj_pins = ctx.shared.inj_pins;
+    let mut fuel = (ctx.shared.efi_status, ctx.shared.efi_cfg, ctx.shared.tables);
+
+    // cebado al dar contacto, se espera a tener lecturas del ADC para la temperatura
+    Systick::delay(100.millis()).await;
+    let clt = sensor_values.lock(|s| s.cooltan_temp);
+    let priming = fuel.lock(|_, cfg, _| get_priming_pulse(clt, &cfg.injection.cranking));
+
+    if let Some(pulse) = priming {
+        inj_pins.lock(|pins| {
+            pins.iny_1.set_high();
+            pins.iny_2.set_high();
+        });
+        Systick::delay(pulse.micros()).await;
+        inj_pins.lock(|pins| {
+            pins.iny_1.set_low();
+            pins.iny_2.set_low();
+        });
+    }
+
+    loop {
+        let (stopped, air_density) = fuel.lock(|es, cfg, _| (es.cycle_status == __rpm_status::STOPPED, cfg.injection.air_density));
+        let sensors = sensor_values.lock(|s| {
+            update_baro(s, &air_density, stopped);
+            *s
+        });
+        let flex = flex_sensor.lock(|f| *f);
+        let now = Systick::now().duration_since_epoch().to_millis();
+
+        fuel.lock(|es, cfg, tables| {
+            es.sensors = sensors;
+            es.sensors.faults.report(&mut es.diagnostics);
+            if cfg.injection.flex_fuel.enabled {
+                let valid = flex.update(&mut es.sensors, &cfg.injection.flex_fuel, now);
+                es.diagnostics.set(DiagnosticCode::FlexSensorFault, !valid);
+            }
+
+            calculate_time_isr(es, cfg, tables, now);
+            calculate_advance(es, cfg, tables);
+
+            inj_scheduler.lock(|scheduler| scheduler.update(cfg, &es.injection, es.has_phase));
+            ign_scheduler.lock(|scheduler| scheduler.update(cfg, &es.ignition));
+        });
+
+        Systick::delay(5.millis()).await;
+    }
+}
+
+pub(crate) fn injection_trigger(mut ctx: app::injection_trigger::Context) {
+    let mut now = 0;
+    ctx.shared.timer4.lock(|t4| { now = t4.now().ticks(); });
+
+    (ctx.shared.timer, ctx.shared.inj_scheduler, ctx.shared.inj_pins).lock(|timer, scheduler, pins| {
+        timer.clear_interrupt(Event::Update);
+
+        scheduler.on_timer(now, pins);
+
+        match scheduler.next_event(now) {
+            Some(delay) => { timer.start(delay.micros()).ok(); }
+            None => { timer.cancel().ok(); }
+        }
+    });
+}
+
+pub(crate) async fn ltft_save(ctx: app::ltft_save::Context<'_>) {
+    let mut efi_cfg = ctx.shared.efi_cfg;
+    let mut ltft = (ctx.shared.efi_status, ctx.shared.tables);
+    let mut memory = (ctx.shared.flash, ctx.shared.flash_info, ctx.shared.crc);
+    let mut last_save = 0;
+
+    loop {
+        Systick::delay(SAVE_CHECK_INTERVAL.millis()).await;
+
+        let now = Systick::now().duration_since_epoch().to_millis();
+        let interval = efi_cfg.lock(|cfg| cfg.injection.ltft.save_interval.max(1)) * 1000;
+
+        // se graba al pararse el motor (o al resetear parado) y en marcha cada `save_interval`,
+        // cada grabacion borra un sector
+        let data = ltft.lock(|es, tables| {
+            let stopped = es.cycle_status == __rpm_status::STOPPED;
+            if !es.ltft.dirty || (!stopped && now.wrapping_sub(last_save) < interval) {
+                return None;
+            }
+            let data = tables.ltft?;
+            es.ltft.dirty = false;
+            es.ltft.sequence = es.ltft.sequence.wrapping_add(1);
+            Some((data, es.ltft.sequence))
+        });
+
+        if let Some((data, sequence)) = data {
+            last_save = now;
+            memory.lock(|flash, flash_info, crc| write_ltft(&data, sequence, flash, flash_info, crc));
+        }
+    }
+}
+
+pub(crate) async fn trip_save(ctx: app::trip_save::Context<'_>) {
+    let mut efi_status = ctx.shared.efi_status;
+    let mut memory = (ctx.shared.flash, ctx.shared.flash_info, ctx.shared.crc);
+    let mut last_save = 0;
+
+    loop {
+        Systick::delay(SAVE_CHECK_INTERVAL.millis()).await;
+
+        let now = Systick::now().duration_since_epoch().to_millis();
+
+        // igual que la LTFT: al pararse el motor (o al resetear parado) y en marcha cada TRIP_SAVE_INTERVAL
+        let trip = efi_status.lock(|es| {
+            let stopped = es.cycle_status == __rpm_status::STOPPED;
+            if !es.trip.dirty || (!stopped && now.wrapping_sub(last_save) < TRIP_SAVE_INTERVAL * 1000) {
+                return None;
+            }
+            es.trip.dirty = false;
+            es.trip.sequence = es.trip.sequence.wrapping_add(1);
+            Some(es.trip)
+        });
+
+        if let Some(trip) = trip {
+            last_save = now;
+            let saved = memory.lock(|flash, flash_info, crc| trip.save(trip.sequence, flash, flash_info, crc));
+            if saved.is_err() {
+                host::debug!("no se pudo serializar el consumo");
+            }
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod engine;
-// pub mod ignition;
\ No newline at end of file
+pub mod ignition;
+pub mod injection;
+pub mod sensors;
+pub mod webserial;
@@ -0,0 +1,106 @@
+use rtic::Mutex;
+use rtic::mutex_prelude::TupleExt04;
+use rtic_monotonics::systick::*;
+
+use crate::app;
+use crate::app::engine::{
+    aux_sensors::{AuxSensorType, MUX_CHANNELS},
+    efi_cfg::BaroSource,
+    sensors::{get_mux_raw, select_baro_sensor, select_mux_channel},
+    tps_calibration::TpsCalibrationStep,
+};
+use crate::app::webserial::{handle_engine::tps_calibration_response, SerialMessage, SerialSender};
+
+// lecturas promediadas por cada extremo del TPS
+const TPS_CALIBRATION_SAMPLES: u32 = 20;
+
+// Systick corre a 10kHz (systick-10khz), menos de un tick se espera con el CPU a 120MHz
+const SYSTICK_TICK_US: u32 = 100;
+const CPU_CYCLES_PER_US: u32 = 120;
+
+/**
+ * @brief espera `time` uS a que asiente la entrada analogica; con Systick::delay se redondearia a 100uS
+ */
+async fn settle(time: u32) {
+    if time < SYSTICK_TICK_US {
+        cortex_m::asm::delay(time * CPU_CYCLES_PER_US);
+    } else {
+        Systick::delay(time.micros()).await;
+    }
+}
+
+/**
+ * @brief graba un extremo del TPS, lo lanza el comando de calibracion por USB (`serial_cmd`)
+ * y le responde con el estado; al tener los dos guarda la calibracion en la flash
+ */
+pub(crate) async fn tps_calibrate(
+    ctx: app::tps_calibrate::Context<'_>,
+    step: TpsCalibrationStep,
+    serial_cmd: SerialMessage,
+    mut sender: SerialSender,
+) {
+    let mut sensors = ctx.shared.sensors;
+    let mut tps_cal = ctx.shared.tps_cal;
+    let mut memory = (ctx.shared.sensor_cfg, ctx.shared.flash, ctx.shared.flash_info, ctx.shared.crc);
+
+    let mut sum = 0.0;
+    for _ in 0..TPS_CALIBRATION_SAMPLES {
+        sum += sensors.lock(|s| s.raw_tps());
+        Systick::delay(10.millis()).await;
+    }
+    let millivolts = sum / TPS_CALIBRATION_SAMPLES as f32;
+
+    let (calibration, state) = tps_cal.lock(|cal| (cal.record(step, millivolts), *cal));
+
+    if let Some(calibration) = calibration {
+        memory.lock(|cfg, flash, flash_info, crc| {
+            cfg.tps = calibration;
+            cfg.save(flash, flash_info, crc);
+        });
+    }
+
+    sender.send(tps_calibration_response(serial_cmd, &state)).await.ok();
+}
+
+/**
+ * @brief recorre los canales del mux de ADC2, los que tengan un sensor asignado se leen
+ * despues de `settle_time` y se publican en `sensors.aux`; con BaroSource::Sensor tambien
+ * lee el sensor de baro propio, que comparte la entrada analogica
+ */
+pub(crate) async fn aux_sensors_scan(ctx: app::aux_sensors_scan::Context<'_>) {
+    let adc = ctx.local.adc;
+    let adc_pins = ctx.local.analog_pins;
+    let mut sensor_cfg = ctx.shared.sensor_cfg;
+    let mut sensors = ctx.shared.sensors;
+    let mut efi_cfg = ctx.shared.efi_cfg;
+
+    loop {
+        let cfg = sensor_cfg.lock(|cfg| cfg.aux);
+
+        if efi_cfg.lock(|efi_cfg| efi_cfg.injection.air_density.baro_source) == BaroSource::Sensor {
+            select_baro_sensor(true, adc_pins);
+            settle(cfg.settle_time).await;
+
+            let millivolts = get_mux_raw(adc_pins, adc);
+            select_baro_sensor(false, adc_pins);
+            sensor_cfg.lock(|sensor_cfg| sensors.lock(|s| s.update_baro_sensor(millivolts, sensor_cfg)));
+        }
+
+        if cfg.enabled {
+            for channel in 0..MUX_CHANNELS {
+                let channel_cfg = cfg.channels[channel];
+                if channel_cfg.sensor == AuxSensorType::None {
+                    continue;
+                }
+
+                select_mux_channel(channel as u8, adc_pins);
+                settle(cfg.settle_time).await;
+
+                let millivolts = get_mux_raw(adc_pins, adc);
+                sensors.lock(|s| s.aux.update(channel, millivolts, &channel_cfg));
+            }
+        }
+
+        Systick::delay(cfg.interval.max(1).millis()).await;
+    }
+}
@@ -0,0 +1,132 @@
+use rtic::Mutex;
+use rtic::mutex_prelude::{TupleExt02, TupleExt03};
+use rtic_monotonics::systick::*;
+use rtic_monotonics::Monotonic;
+use rtic_sync::channel::Receiver;
+use stm32f4xx_hal::otg_fs::UsbBusType;
+use usb_device::{class_prelude::UsbClass, UsbError};
+
+use crate::app;
+use crate::app::logging::host;
+use crate::app::webserial::{
+    finish_message,
+    handle_engine::{engine_cdc_callback, get_tps_calibration_step},
+    handle_realtime_data::realtime_data_cdc_callback,
+    process_command,
+    send_message,
+    SerialCode,
+    SerialMessage,
+    SerialSender,
+    SerialStatus,
+    CDC_BUFF_CAPACITY,
+    PROTOCOL_ENGINE,
+    PROTOCOL_PING,
+    PROTOCOL_REALTIME,
+};
+
+// mS sin recibir nada para descartar un mensaje a medias y volver a alinear
+const CDC_RX_TIMEOUT: u32 = 50;
+
+// reintentos (de 1mS) con el endpoint lleno antes de descartar la respuesta, si no hay nadie leyendo
+const CDC_TX_RETRIES: u32 = 100;
+
+/**
+ * @brief interrupcion del USB, junta lo recibido hasta completar un mensaje y se lo pasa a cdc_receiver
+ */
+pub(crate) fn usb_handler(ctx: app::usb_handler::Context) {
+    let buffer = ctx.local.cdc_input_buffer;
+    let last_rx = ctx.local.cdc_last_rx;
+    let sender = ctx.local.cdc_sender;
+
+    (ctx.shared.usb_dev, ctx.shared.usb_cdc, ctx.shared.usb_web).lock(|usb_dev, usb_cdc, usb_web| {
+        let classes: &mut [&mut dyn UsbClass<UsbBusType>] = &mut [usb_web, usb_cdc];
+        if !usb_dev.poll(classes) {
+            return;
+        }
+
+        let mut data = [0u8; 64];
+        let count = match usb_cdc.read(&mut data) {
+            Ok(count) => count,
+            Err(_) => return,
+        };
+
+        let now = Systick::now().duration_since_epoch().to_millis();
+        if now.wrapping_sub(*last_rx) > CDC_RX_TIMEOUT {
+            buffer.clear();
+        }
+        *last_rx = now;
+
+        for &byte in &data[..count] {
+            buffer.push(byte);
+
+            if buffer.is_full() {
+                match process_command(buffer) {
+                    Some(message) => {
+                        // si todavia esta respondiendo el anterior se descarta, el host reintenta
+                        if app::cdc_receiver::spawn(message, sender.clone()).is_err() {
+                            host::debug!("USB ocupado, mensaje descartado");
+                        }
+                    }
+                    None => host::debug!("mensaje USB con CRC invalido"),
+                }
+                buffer.clear();
+            }
+        }
+    });
+}
+
+/**
+ * @brief manda por USB las respuestas encoladas
+ */
+pub(crate) async fn send2usb(ctx: app::send2usb::Context<'_>, mut receiver: Receiver<'static, SerialMessage, CDC_BUFF_CAPACITY>) {
+    let mut usb_cdc = ctx.shared.usb_cdc;
+
+    while let Ok(message) = receiver.recv().await {
+        let data = finish_message(message);
+        let mut written = 0;
+        let mut retries = 0;
+
+        while written < data.len() {
+            match usb_cdc.lock(|cdc| cdc.write(&data[written..])) {
+                Ok(count) => written += count,
+                Err(UsbError::WouldBlock) if retries < CDC_TX_RETRIES => {
+                    retries += 1;
+                    Systick::delay(1.millis()).await;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/**
+ * @brief atiende un mensaje recibido por USB segun su protocolo
+ */
+pub(crate) async fn cdc_receiver(ctx: app::cdc_receiver::Context<'_>, serial_cmd: SerialMessage, mut sender: SerialSender) {
+    let response = match serial_cmd.protocol {
+        PROTOCOL_PING => serial_cmd.reply().with_status(SerialStatus::Ok, SerialCode::None),
+        PROTOCOL_REALTIME => {
+            let mut efi_status = ctx.shared.efi_status;
+            efi_status.lock(|es| realtime_data_cdc_callback(serial_cmd, es))
+        }
+        PROTOCOL_ENGINE => match get_tps_calibration_step(serial_cmd.command) {
+            // promedia lecturas un rato, responde tps_calibrate al terminar
+            Some(step) => {
+                if app::tps_calibrate::spawn(step, serial_cmd, sender.clone()).is_err() {
+                    send_message(&mut sender, SerialStatus::Error, SerialCode::Busy, serial_cmd).await;
+                }
+                return;
+            }
+            None => {
+                let mut engine = (ctx.shared.efi_status, ctx.shared.tables);
+                engine.lock(|es, tables| engine_cdc_callback(serial_cmd, es, tables))
+            }
+        },
+        _ => {
+            send_message(&mut sender, SerialStatus::Error, SerialCode::UnknownProtocol, serial_cmd).await;
+            return;
+        }
+    };
+
+    sender.send(response).await.ok();
+}
@@ -0,0 +1,63 @@
+use crate::app::{
+    engine::{
+        engine_status::EngineStatus,
+        tps_calibration::{TpsCalibration, TpsCalibrationStep},
+    },
+    injection::ltft::reset_ltft,
+    memory::tables::Tables,
+    webserial::{SerialCode, SerialMessage, SerialStatus},
+};
+
+// comandos
+// borra la LTFT aprendida, se graba en la flash con el motor parado
+pub const ENGINE_LTFT_RESET: u8 = 1;
+// borra el consumo parcial del trip computer, el total queda
+pub const ENGINE_TRIP_RESET: u8 = 2;
+// graban un extremo del TPS, la respuesta (TpsCalibration) la manda tps_calibrate al terminar
+pub const ENGINE_TPS_CALIBRATE_CLOSED: u8 = 3;
+pub const ENGINE_TPS_CALIBRATE_WIDE_OPEN: u8 = 4;
+
+/**
+ * @brief paso de la calibracion del TPS que pide el comando, None si es otro comando
+ */
+pub fn get_tps_calibration_step(command: u8) -> Option<TpsCalibrationStep> {
+    match command {
+        ENGINE_TPS_CALIBRATE_CLOSED => Some(TpsCalibrationStep::Closed),
+        ENGINE_TPS_CALIBRATE_WIDE_OPEN => Some(TpsCalibrationStep::WideOpen),
+        _ => None,
+    }
+}
+
+/**
+ * @brief estado de la calibracion del TPS, serializado con postcard en el payload
+ */
+pub fn tps_calibration_response(serial_cmd: SerialMessage, calibration: &TpsCalibration) -> SerialMessage {
+    let mut response = serial_cmd.reply();
+
+    match postcard::to_slice(calibration, &mut response.payload).is_ok() {
+        true => response.with_status(SerialStatus::Ok, SerialCode::None),
+        false => response.with_status(SerialStatus::Error, SerialCode::SerializeError),
+    }
+}
+
+/**
+ * @brief comandos sobre el estado del motor que se responden en el momento, sin payload
+ */
+pub fn engine_cdc_callback(serial_cmd: SerialMessage, es: &mut EngineStatus, tables: &mut Tables) -> SerialMessage {
+    let response = serial_cmd.reply();
+
+    match serial_cmd.command {
+        ENGINE_LTFT_RESET => {
+            if tables.ltft.is_none() {
+                return response.with_status(SerialStatus::Error, SerialCode::TableNotLoaded);
+            }
+            reset_ltft(tables, &mut es.ltft);
+            response.with_status(SerialStatus::Ok, SerialCode::None)
+        }
+        ENGINE_TRIP_RESET => {
+            es.trip.reset_trip();
+            response.with_status(SerialStatus::Ok, SerialCode::None)
+        }
+        _ => response.with_status(SerialStatus::Error, SerialCode::UnknownCmd),
+    }
+}
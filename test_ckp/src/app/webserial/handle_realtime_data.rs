@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+use crate::app::{
+    engine::engine_status::EngineStatus,
+    webserial::{SerialCode, SerialMessage, SerialStatus},
+};
+
+// comandos
+pub const REALTIME_STATUS: u8 = 1;
+
+// lo que se muestra en vivo, serializado con postcard en el payload
+#[derive(Serialize, Debug)]
+pub struct RealTimeData {
+    pub rpm: i32,
+    pub cycle_status: u8,
+    pub map: f32,
+    pub tps: f32,
+    pub clt: f32,
+    pub iat: f32,
+    pub batt: f32,
+    pub lambda: f32,
+    pub target_lambda: f32,
+    // uS
+    pub injection_bank_1_time: f32,
+    pub injection_bank_2_time: f32,
+    // % del tiempo disponible por inyeccion en cada banco
+    pub duty_cycle_1: f32,
+    pub duty_cycle_2: f32,
+    // grados APMS
+    pub advance: f32,
+    pub injection_status: u8,
+    // bits de DiagnosticCode
+    pub diagnostics: u32,
+    // L/h
+    pub fuel_flow_rate: f32,
+    // mL desde el ultimo reset / en total
+    pub trip_fuel: u32,
+    pub total_fuel: u32,
+}
+
+pub fn get_realtime_data(es: &EngineStatus) -> RealTimeData {
+    RealTimeData {
+        rpm: es.rpm,
+        cycle_status: es.cycle_status as u8,
+        map: es.sensors.map,
+        tps: es.sensors.tps,
+        clt: es.sensors.cooltan_temp,
+        iat: es.sensors.air_temp,
+        batt: es.sensors.batt,
+        lambda: es.sensors.lambda,
+        target_lambda: es.injection.target_lambda,
+        injection_bank_1_time: es.injection.injection_bank_1_time,
+        injection_bank_2_time: es.injection.injection_bank_2_time,
+        duty_cycle_1: es.injection.duty_cycle_1,
+        duty_cycle_2: es.injection.duty_cycle_2,
+        advance: es.ignition.advance,
+        injection_status: es.injection.injection_status as u8,
+        diagnostics: es.diagnostics.active,
+        fuel_flow_rate: es.injection.fuel_flow_rate,
+        trip_fuel: es.trip.trip_fuel,
+        total_fuel: es.trip.total_fuel,
+    }
+}
+
+pub fn realtime_data_cdc_callback(serial_cmd: SerialMessage, es: &EngineStatus) -> SerialMessage {
+    let mut response = serial_cmd.reply();
+
+    match serial_cmd.command {
+        REALTIME_STATUS => {
+            let serialized = postcard::to_slice(&get_realtime_data(es), &mut response.payload).is_ok();
+
+            match serialized {
+                true => response.with_status(SerialStatus::Ok, SerialCode::None),
+                false => response.with_status(SerialStatus::Error, SerialCode::SerializeError),
+            }
+        }
+        _ => response.with_status(SerialStatus::Error, SerialCode::UnknownCmd),
+    }
+}
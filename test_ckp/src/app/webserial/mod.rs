@@ -0,0 +1,127 @@
+use rtic_sync::channel::Sender;
+
+use crate::app::util::crc16;
+
+pub mod handle_engine;
+pub mod handle_realtime_data;
+
+// mensajes de largo fijo: protocolo, comando, estado, codigo, payload y CRC16 (big endian)
+pub const MESSAGE_SIZE: usize = 128;
+pub const PAYLOAD_SIZE: usize = 122;
+
+// respuestas encoladas para mandar por USB
+pub const CDC_BUFF_CAPACITY: usize = 30;
+
+pub type SerialSender = Sender<'static, SerialMessage, CDC_BUFF_CAPACITY>;
+
+// SerialMessage.protocol, 1 (tablas) y 3 (PMIC) quedan reservados
+pub const PROTOCOL_PING: u8 = 0;
+pub const PROTOCOL_REALTIME: u8 = 2;
+pub const PROTOCOL_ENGINE: u8 = 4;
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SerialStatus {
+    Error = 0,
+    Ok,
+    // parte de una respuesta que sigue en el proximo mensaje
+    DataChunk,
+    DataChunkEnd,
+}
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SerialCode {
+    None = 0,
+    UnknownProtocol,
+    UnknownCmd,
+    // no entra en el payload
+    SerializeError,
+    // la tabla no esta cargada en memoria (CRC invalido o nunca grabada)
+    TableNotLoaded,
+    // ya hay una operacion igual en curso (calibracion del TPS)
+    Busy,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SerialMessage {
+    pub protocol: u8,
+    pub command: u8,
+    pub status: u8,
+    pub code: u8,
+    pub payload: [u8; PAYLOAD_SIZE],
+    pub crc: u16,
+}
+
+impl SerialMessage {
+    /**
+     * @brief respuesta vacia al mismo protocolo / comando
+     */
+    pub fn reply(&self) -> SerialMessage {
+        SerialMessage {
+            protocol: self.protocol,
+            command: self.command,
+            status: 0,
+            code: 0,
+            payload: [0; PAYLOAD_SIZE],
+            crc: 0,
+        }
+    }
+
+    pub fn with_status(mut self, status: SerialStatus, code: SerialCode) -> SerialMessage {
+        self.status = status as u8;
+        self.code = code as u8;
+        self
+    }
+}
+
+/**
+ * @brief arma el mensaje desde los bytes recibidos, None si el largo o el CRC no coinciden
+ */
+pub fn process_command(buf: &[u8]) -> Option<SerialMessage> {
+    if buf.len() != MESSAGE_SIZE {
+        return None;
+    }
+
+    let crc = u16::from_be_bytes([buf[MESSAGE_SIZE - 2], buf[MESSAGE_SIZE - 1]]);
+    if crc16(buf, (MESSAGE_SIZE - 2) as u8) != crc {
+        return None;
+    }
+
+    let mut payload = [0; PAYLOAD_SIZE];
+    payload.copy_from_slice(&buf[4..4 + PAYLOAD_SIZE]);
+
+    Some(SerialMessage {
+        protocol: buf[0],
+        command: buf[1],
+        status: buf[2],
+        code: buf[3],
+        payload,
+        crc,
+    })
+}
+
+/**
+ * @brief bytes a mandar por USB, con el CRC calculado
+ */
+pub fn finish_message(message: SerialMessage) -> [u8; MESSAGE_SIZE] {
+    let mut buf = [0; MESSAGE_SIZE];
+
+    buf[0] = message.protocol;
+    buf[1] = message.command;
+    buf[2] = message.status;
+    buf[3] = message.code;
+    buf[4..4 + PAYLOAD_SIZE].copy_from_slice(&message.payload);
+
+    let crc = crc16(&buf, (MESSAGE_SIZE - 2) as u8);
+    buf[MESSAGE_SIZE - 2..].copy_from_slice(&crc.to_be_bytes());
+
+    buf
+}
+
+/**
+ * @brief encola la respuesta a `message` con su estado y codigo
+ */
+pub async fn send_message(sender: &mut SerialSender, status: SerialStatus, code: SerialCode, message: SerialMessage) {
+    sender.send(message.reply().with_status(status, code)).await.ok();
+}
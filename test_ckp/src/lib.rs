@@ -0,0 +1,7 @@
+// matematica del motor que no depende del micro (tablas, densidad del aire, inyectores, encendido, LTFT,
+// termistores, filtros, fallas de sensores y calibraciones),
+// se compila aparte para poder correr los tests en la PC:
+//     cargo test --lib --target x86_64-unknown-linux-gnu
+#![cfg_attr(not(test), no_std)]
+
+pub mod math;
@@ -14,12 +14,13 @@
 
 use rtic;
 use rtic_monotonics::systick::*;
+use rtic_monotonics::Monotonic;
 use rtic_sync::{channel::*, make_channel};
 
 use w25q::series25::FlashInfo;
 use arrayvec::ArrayVec;
 
-use usb_device::{bus::UsbBusAllocator, device::UsbDevice};
+use usb_device::{bus::UsbBusAllocator, device::UsbDevice, prelude::*};
 use usbd_serial::SerialPort;
 use usbd_webusb::{url_scheme, WebUsb};
 
@@ -34,7 +35,7 @@ use stm32f4xx_hal::{
     gpio::{Edge, Input},
     otg_fs,
     otg_fs::{USB, UsbBusType},
-    pac::{ADC1, TIM13, TIM2, TIM3, TIM5,DMA2,ADC2},
+    pac::{ADC1, TIM10, TIM13, TIM2, TIM3, TIM5,DMA2,ADC2},
     prelude::*,
     spi::*,
     timer::{self, Event},
@@ -43,6 +44,10 @@ use stm32f4xx_hal::{
 
 use panic_halt as _;
 
+// memoria de los endpoints del USB, static mut como en los ejemplos de la HAL
+// (un singleton! no puede prestarle a otro singleton!)
+static mut EP_MEMORY: [u32; 1024] = [0; 1024];
+
 #[rtic::app(device = stm32f4xx_hal::pac, peripherals = true, dispatchers = [TIM4, TIM7, TIM8_CC])]
 mod app {
     use my_module::blink2;
@@ -50,10 +55,14 @@ mod app {
     use crate::app::{
         engine::{
             cpwm::VRStatus,
+            diagnostics::DiagnosticCode,
             efi_cfg::{EngineConfig, get_default_efi_cfg},
             engine_status::{EngineStatus, get_default_engine_status},
+            flex::{FlexSensor, CAPTURE_PERIOD},
+            sensor_cfg::{get_default_sensor_cfg, SensorConfig},
+            tps_calibration::{TpsCalibration, TpsCalibrationStep},
             pmic::{PMIC, PmicT},
-            sensors::{get_sensor_raw, SensorTypes, SensorValues},
+            sensors::{SensorTypes, SensorValues},
         },
         gpio::{
             ADCMapping,
@@ -65,24 +74,20 @@ mod app {
             RelayMapping,
             StepperMapping,
         },
-        injection::{calculate_time_isr, injection_setup},
-        ignition::{ignition_checks, ignition_trigger},
+        injection::{calculate_time_isr, injection_setup, scheduler::InjectionScheduler},
+        ignition::{ignition_setup, scheduler::IgnitionScheduler},
         logging::host,
         memory::tables::{SpiT, Tables},
-        webserial::{
-            finish_message,
-            handle_engine::engine_cdc_callback,
-            handle_pmic::pmic_cdc_callback,
-            handle_realtime_data::realtime_data_cdc_callback,
-            handle_tables::table_cdc_callback,
-            send_message,
-            SerialMessage,
-            SerialStatus,
-        },
-        tasks::{engine::ckp_checks/* , engine::motor_checks,  ignition::ignition_schedule */},
+        util::get_serial_str,
+        webserial::{SerialMessage, CDC_BUFF_CAPACITY, MESSAGE_SIZE},
+        tasks::{engine::ckp_checks/* , engine::motor_checks */},
     };
     use crate::app::engine::sensors;
-    use crate::app::tasks::engine::ckp_trigger;
+    use crate::app::tasks::engine::{ckp_trigger, flex_trigger};
+    use crate::app::tasks::ignition::ignition_trigger;
+    use crate::app::tasks::injection::{injection_checks, injection_trigger, ltft_save, trip_save};
+    use crate::app::tasks::sensors::{aux_sensors_scan, tps_calibrate};
+    use crate::app::tasks::webserial::{cdc_receiver, send2usb, usb_handler};
 
     use super::*;
 
@@ -90,10 +95,13 @@ mod app {
     // pub mod debug;
     pub mod engine;
     pub mod gpio;
+    pub mod ignition;
+    pub mod injection;
     pub mod logging;
     pub mod memory;
     pub mod util;
     pub mod tasks;
+    pub mod webserial;
 
 
 
@@ -128,11 +136,21 @@ mod app {
         flash_info: FlashInfo,
         tables: Tables,
         sensors: SensorValues,
+        sensor_cfg: SensorConfig,
+        tps_cal: TpsCalibration,
         pmic: PmicT,
+        inj_scheduler: InjectionScheduler,
+        ign_scheduler: IgnitionScheduler,
+        flex: FlexSensor,
 
         // CKP/SYNC
         ckp: VRStatus,
         ignition_running: bool,
+
+        // USB
+        usb_cdc: SerialPort<'static, UsbBusType>,
+        usb_web: WebUsb<UsbBusType>,
+        usb_dev: UsbDevice<'static, UsbBusType>,
     }
 
     #[local]
@@ -143,10 +161,15 @@ mod app {
 
         // EFI Related:
         ckp: stm32f4xx_hal::gpio::PC6<Input>,
+        // lo usa flex_trigger por registros, queda aca para que nadie mas tome TIM10
+        flex_timer: timer::CounterUs<TIM10>,
         adc: Adc<ADC2>,
         analog_pins: ADCMapping,
 
-        // cdc_sender: Sender<'static, u32, 8>,
+        // USB
+        cdc_sender: Sender<'static, SerialMessage, CDC_BUFF_CAPACITY>,
+        cdc_input_buffer: ArrayVec<u8, MESSAGE_SIZE>,
+        cdc_last_rx: u32,
 
         // TODO: Remove
         state: bool,
@@ -156,8 +179,6 @@ mod app {
         ign_channel_1: bool,
     }
 
-    const CDC_BUFF_CAPACITY: usize = 30;
-
     #[init()]
     fn init(mut cx: init::Context) -> (Shared, Local) {
         // Setup clocks
@@ -286,9 +307,26 @@ mod app {
             wue: None,
             ase_taper: None,
             ase_intensity: None,
+            ltft: None,
+            ethanol_advance: None,
+            staging: None,
+            eoi: None,
         };
 
         efi_cfg.read(&mut flash, &flash_info, &mut crc);
+        let mut sensor_cfg = get_default_sensor_cfg();
+        sensor_cfg.read(&mut flash, &flash_info, &mut crc);
+        injection_setup(&mut table, &mut _efi_status.ltft, &mut flash, &flash_info, &mut crc);
+        ignition_setup(&mut table, &mut flash, &flash_info, &mut crc);
+        _efi_status.trip.read(&mut flash, &flash_info, &mut crc);
+        let mut inj_scheduler = InjectionScheduler::new();
+        let ign_scheduler = IgnitionScheduler::new();
+
+        // con dos inyectores y dos bobinas los cilindros que comparten salida usan el promedio de sus correcciones
+        if !efi_cfg.cylinder_trims_supported() {
+            host::debug!("correcciones por cilindro distintas en cilindros que comparten salida, se usa el promedio");
+            _efi_status.diagnostics.set(DiagnosticCode::CylinderTrimUnsupported, true);
+        }
 
         let mut sensors = SensorValues::new();
 
@@ -299,12 +337,61 @@ mod app {
 
         let mut ckp_status = VRStatus::new();
 
+        // USB
+        let usb = USB {
+            usb_global: device.OTG_FS_GLOBAL,
+            usb_device: device.OTG_FS_DEVICE,
+            usb_pwrclk: device.OTG_FS_PWRCLK,
+            // en gpio.rs los nombres estan cruzados: PA11 (usb_dp) es D- y PA12 (usb_dm) es D+
+            pin_dm: gpio_config.usb_dp,
+            pin_dp: gpio_config.usb_dm,
+            hclk: _clocks.hclk(),
+        };
+
+        let usb_bus: &'static UsbBusAllocator<UsbBusType> =
+            cortex_m::singleton!(: UsbBusAllocator<UsbBusType> = otg_fs::UsbBus::new(usb, unsafe { &mut EP_MEMORY })).unwrap();
+
+        let usb_cdc = SerialPort::new(usb_bus);
+        let usb_web = WebUsb::new(usb_bus, url_scheme::HTTPS, "churrosoft.ar");
+        // TODO: VID/PID de prueba de pid.codes, falta pedir uno propio
+        let usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x1209, 0x0001))
+            .manufacturer("Churrosoft")
+            .product("OpenEFI")
+            .serial_number(get_serial_str())
+            .device_release(0x0200)
+            .self_powered(false)
+            .max_power(250)
+            .max_packet_size_0(64)
+            .build();
+
+        let (usb_sender, usb_receiver) = make_channel!(SerialMessage, CDC_BUFF_CAPACITY);
+        send2usb::spawn(usb_receiver).ok();
+
+        // sensor de etanol en aux in_7 (TIM10 CH1), contador libre de 16 bits a 1MHz
+        // con captura por hardware, flex_trigger alterna el flanco en cada captura
+        let mut flex_timer: timer::CounterUs<TIM10> = device.TIM10.counter_us(&_clocks);
+        if efi_cfg.injection.flex_fuel.enabled {
+            flex_timer.start(CAPTURE_PERIOD.micros()).unwrap();
+
+            let tim10 = unsafe { &*TIM10::ptr() };
+            // CC1S = 01: CH1 como entrada en TI1
+            tim10.ccmr1_input().modify(|r, w| unsafe { w.bits((r.bits() & !0b11) | 0b01) });
+            tim10.ccer.modify(|_, w| w.cc1p().clear_bit().cc1e().set_bit());
+            tim10.dier.modify(|_, w| w.cc1ie().set_bit());
+        }
+
 
         // DEMO
         // Schedule the blinking task
         blink::spawn().ok();
         blink2::spawn().ok();
 
+        polling_adc::spawn().ok();
+        injection_checks::spawn().ok();
+        ltft_save::spawn().ok();
+        trip_save::spawn().ok();
+        aux_sensors_scan::spawn().ok();
+
 
         let mut watchdog = IndependentWatchdog::new(device.IWDG);
         // se puede desactivar en debug
@@ -327,6 +414,8 @@ mod app {
             relay_pins: gpio_config.relay,
             stepper_pins: gpio_config.stepper,
             sensors,
+            sensor_cfg,
+            tps_cal: TpsCalibration::new(),
 
             // CORE:
             crc,
@@ -340,10 +429,18 @@ mod app {
             efi_status: _efi_status,
             tables: table,
             pmic,
+            inj_scheduler,
+            ign_scheduler,
+            flex: FlexSensor::new(),
 
             //CKP/SYNC
             ckp: ckp_status,
             ignition_running: false,
+
+            // USB
+            usb_cdc,
+            usb_web,
+            usb_dev,
         }, Local {
             watchdog,
 
@@ -351,8 +448,14 @@ mod app {
 
             adc,
             ckp,
+            flex_timer,
             analog_pins: gpio_config.adc,
 
+            // USB
+            cdc_sender: usb_sender,
+            cdc_input_buffer: ArrayVec::new(),
+            cdc_last_rx: 0,
+
             state: false,
             state2: false,
             // ignition,
@@ -378,11 +481,11 @@ mod app {
                     adc.start_conversion();
                 });
             });
-            Systick::delay(250.millis()).await;
+            Systick::delay(10.millis()).await;
         }
     }
 
-    #[task(binds = DMA2_STREAM0, shared = [adc_transfer,sensors], local = [adc_buffer])]
+    #[task(binds = DMA2_STREAM0, shared = [adc_transfer,sensors,sensor_cfg], local = [adc_buffer])]
     fn sensors_adc_dma(mut cx: sensors_adc_dma::Context) {
 
         let (buffer, sample_to_millivolts) = cx.shared.adc_transfer.lock(|transfer| {
@@ -409,12 +512,15 @@ mod app {
         // If we don't do this before the next transfer, we'll get a panic
         *cx.local.adc_buffer = Some(buffer);
 
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_tps),SensorTypes::TPS) });
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_clt),SensorTypes::CooltanTemp) });
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_iat),SensorTypes::AirTemp) });
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_map),SensorTypes::MAP) });
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_o2),SensorTypes::ExternalLambda) });
-        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_vbatt),SensorTypes::BatteryVoltage) });
+        let cfg = cx.shared.sensor_cfg.lock(|cfg| *cfg);
+        let now = Systick::now().duration_since_epoch().to_millis();
+
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_tps),SensorTypes::TPS, &cfg, now) });
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_clt),SensorTypes::CooltanTemp, &cfg, now) });
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_iat),SensorTypes::AirTemp, &cfg, now) });
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_map),SensorTypes::MAP, &cfg, now) });
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_o2),SensorTypes::ExternalLambda, &cfg, now) });
+        cx.shared.sensors.lock(|s| { s.update(sample_to_millivolts(raw_vbatt),SensorTypes::BatteryVoltage, &cfg, now) });
 
     }
 
@@ -440,40 +546,40 @@ mod app {
         }
     }
 
-    //TODO: reciclar para encendido
-    #[task(binds = TIM2, local = [], shared = [timer, led])]
-    fn timer2_exp(mut ctx: timer2_exp::Context) {
-        ctx.shared.timer.lock(|tim| tim.clear_interrupt(Event::Update));
-
-        ctx.shared.led.lock(|l| l.led_0.toggle());
-    }
-
-    #[task(binds = TIM3, local = [], shared = [timer3, led])]
-    fn timer3_exp(mut ctx: timer3_exp::Context) {
-        ctx.shared.timer3.lock(|tim| {
-            tim.clear_interrupt(Event::Update);
-            tim.cancel().unwrap();
-        });
-
-        ctx.shared.led.lock(|l| l.led_check.set_high());
-    }
-
     // Externally defined tasks
     extern "Rust" {
 
         // from: https://github.com/noisymime/speeduino/blob/master/speeduino/decoders.ino#L453
-        #[task(binds = EXTI9_5, local = [ckp], shared = [led, efi_status, flash_info, efi_cfg, timer, timer3, timer4, ckp, ign_pins], priority = 5)]
+        #[task(binds = EXTI9_5, local = [ckp], shared = [led, efi_status, flash_info, efi_cfg, timer, timer3, timer4, ckp, ign_pins, inj_scheduler, ign_scheduler], priority = 5)]
         fn ckp_trigger(ctx: ckp_trigger::Context);
         #[task(shared = [efi_cfg, ckp, timer4, efi_status, ignition_running],priority = 3)]
         async fn ckp_checks(ctx: ckp_checks::Context);
-
-        //
-        // #[task(
-        // shared = [led, efi_status, efi_cfg, timer3, timer4, ckp, ign_pins],
-        // priority = 4
-        // )]
-        // async fn ignition_schedule(ctx: ignition_schedule::Context);
-        //
+        #[task(binds = TIM1_UP_TIM10, local = [flex_timer], shared = [flex], priority = 4)]
+        fn flex_trigger(ctx: flex_trigger::Context);
+
+        #[task(binds = TIM2, shared = [timer, timer4, inj_scheduler, inj_pins], priority = 5)]
+        fn injection_trigger(ctx: injection_trigger::Context);
+        #[task(shared = [efi_cfg, efi_status, sensors, flex, tables, inj_scheduler, ign_scheduler, inj_pins], priority = 2)]
+        async fn injection_checks(ctx: injection_checks::Context);
+
+        #[task(binds = TIM3, shared = [timer3, timer4, ign_scheduler, ign_pins], priority = 5)]
+        fn ignition_trigger(ctx: ignition_trigger::Context);
+        #[task(shared = [efi_cfg, efi_status, tables, flash, flash_info, crc], priority = 1)]
+        async fn ltft_save(ctx: ltft_save::Context);
+        #[task(shared = [efi_status, flash, flash_info, crc], priority = 1)]
+        async fn trip_save(ctx: trip_save::Context);
+
+        #[task(shared = [sensors, sensor_cfg, tps_cal, flash, flash_info, crc], priority = 1)]
+        async fn tps_calibrate(ctx: tps_calibrate::Context, step: TpsCalibrationStep, serial_cmd: SerialMessage, sender: Sender<'static, SerialMessage, CDC_BUFF_CAPACITY>);
+        #[task(local = [adc, analog_pins], shared = [efi_cfg, sensors, sensor_cfg], priority = 1)]
+        async fn aux_sensors_scan(ctx: aux_sensors_scan::Context);
+
+        #[task(binds = OTG_FS, local = [cdc_input_buffer, cdc_last_rx, cdc_sender], shared = [usb_dev, usb_cdc, usb_web], priority = 2)]
+        fn usb_handler(ctx: usb_handler::Context);
+        #[task(shared = [usb_cdc], priority = 1)]
+        async fn send2usb(ctx: send2usb::Context, receiver: Receiver<'static, SerialMessage, CDC_BUFF_CAPACITY>);
+        #[task(shared = [efi_status, tables], priority = 1)]
+        async fn cdc_receiver(ctx: cdc_receiver::Context, serial_cmd: SerialMessage, sender: Sender<'static, SerialMessage, CDC_BUFF_CAPACITY>);
     }
 
     // Externally defined tasks
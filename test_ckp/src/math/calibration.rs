@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::tables::{get_plot_value, PlotData};
+
+// fondo de escala del ADC, los sensores de 5V entran con divisor
+pub const ADC_FULL_SCALE_MV: f32 = 3300.0;
+
+// mV en el pin del ADC -> unidad del sensor
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub enum Calibration {
+    // valor = offset + gain * mV
+    Linear { offset: f32, gain: f32 },
+    // x: mV, y: valor
+    Piecewise(PlotData),
+}
+
+/**
+ * @brief recta que pasa por (mv_low, value_low) y (mv_high, value_high)
+ */
+pub fn linear_between(mv_low: f32, value_low: f32, mv_high: f32, value_high: f32) -> Calibration {
+    let span = mv_high - mv_low;
+    let gain = if span != 0.0 { (value_high - value_low) / span } else { 0.0 };
+
+    Calibration::Linear {
+        offset: value_low - gain * mv_low,
+        gain,
+    }
+}
+
+/**
+ * @brief mV en el pin -> unidad del sensor
+ */
+pub fn apply_calibration(millivolts: f32, calibration: &Calibration) -> f32 {
+    match calibration {
+        Calibration::Linear { offset, gain } => offset + gain * millivolts,
+        Calibration::Piecewise(curve) => get_plot_value(curve, millivolts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= 0.001, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn line_through_points() {
+        let calibration = linear_between(500.0, 0.0, 4500.0, 100.0);
+
+        assert_close(apply_calibration(500.0, &calibration), 0.0);
+        assert_close(apply_calibration(2500.0, &calibration), 50.0);
+        assert_close(apply_calibration(4500.0, &calibration), 100.0);
+    }
+
+    #[test]
+    fn same_points_is_flat() {
+        let calibration = linear_between(1000.0, 20.0, 1000.0, 80.0);
+
+        assert_close(apply_calibration(0.0, &calibration), 20.0);
+        assert_close(apply_calibration(3000.0, &calibration), 20.0);
+    }
+}
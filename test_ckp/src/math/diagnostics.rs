@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum DiagnosticCode {
+    // el combustible pedido no entra en el tiempo disponible del inyector
+    InjectorDutyOverflow = 0,
+    // sensor de etanol sin señal o fuera de rango, se usa fallback_ethanol
+    FlexSensorFault = 1,
+    // sensores fuera de su ventana valida, se usa el valor sustituto
+    MapSensorFault = 2,
+    TpsSensorFault = 3,
+    CltSensorFault = 4,
+    IatSensorFault = 5,
+    BatterySensorFault = 6,
+    LambdaSensorFault = 7,
+    // hay cilindros que comparten inyector o bobina con correcciones distintas, se usa el promedio
+    CylinderTrimUnsupported = 8,
+}
+
+// cada bit es un DiagnosticCode activo
+#[derive(Serialize, Debug, Default, Copy, Clone)]
+pub struct Diagnostics {
+    pub active: u32,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { active: 0 }
+    }
+
+    pub fn set(&mut self, code: DiagnosticCode, active: bool) {
+        if active {
+            self.active |= 1 << code as u8;
+        } else {
+            self.active &= !(1 << code as u8);
+        }
+    }
+
+    pub fn is_active(&self, code: DiagnosticCode) -> bool {
+        self.active & (1 << code as u8) != 0
+    }
+
+    pub fn any(&self) -> bool {
+        self.active != 0
+    }
+}
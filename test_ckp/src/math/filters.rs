@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::sensor_faults::SensorTypes;
+
+// historial maximo para promedio y mediana
+pub const MAX_FILTER_SAMPLES: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum FilterConfig {
+    None,
+    // pasa bajos exponencial, alpha de 0 a 1 (1 = sin filtro)
+    Ema { alpha: f32 },
+    // promedio de las ultimas `samples` lecturas
+    MovingAverage { samples: u8 },
+    // mediana de las ultimas `samples` lecturas, saca picos sueltos
+    Median { samples: u8 },
+    // maximo cambio por lectura, en mV
+    RateLimit { max_step: f32 },
+}
+
+impl FilterConfig {
+    /**
+     * @brief lleva los parametros a su rango valido, fuera de rango la EMA diverge
+     * y el limite de cambio negativo no tiene sentido; devuelve true si cambio algo
+     */
+    pub fn validate(&mut self) -> bool {
+        let valid = match *self {
+            FilterConfig::Ema { alpha } if alpha.is_nan() => FilterConfig::None,
+            FilterConfig::Ema { alpha } => FilterConfig::Ema { alpha: alpha.clamp(0.0, 1.0) },
+            FilterConfig::RateLimit { max_step } if max_step.is_nan() => FilterConfig::None,
+            FilterConfig::RateLimit { max_step } if max_step < 0.0 => FilterConfig::RateLimit { max_step: -max_step },
+            other => other,
+        };
+
+        let changed = valid != *self;
+        *self = valid;
+        changed
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SensorFiltersConfig {
+    pub map: FilterConfig,
+    pub tps: FilterConfig,
+    pub clt: FilterConfig,
+    pub iat: FilterConfig,
+    pub batt: FilterConfig,
+    pub lambda: FilterConfig,
+}
+
+impl SensorFiltersConfig {
+    pub fn get(&self, sensor: SensorTypes) -> &FilterConfig {
+        match sensor {
+            SensorTypes::MAP => &self.map,
+            SensorTypes::TPS => &self.tps,
+            SensorTypes::CooltanTemp => &self.clt,
+            SensorTypes::AirTemp => &self.iat,
+            SensorTypes::BatteryVoltage => &self.batt,
+            SensorTypes::ExternalLambda => &self.lambda,
+        }
+    }
+
+    /**
+     * @brief valida el filtro de cada sensor (ver FilterConfig::validate), true si cambio alguno
+     */
+    pub fn validate(&mut self) -> bool {
+        let mut changed = false;
+        for filter in [&mut self.map, &mut self.tps, &mut self.clt, &mut self.iat, &mut self.batt, &mut self.lambda] {
+            changed |= filter.validate();
+        }
+        changed
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SensorFilter {
+    history: [f32; MAX_FILTER_SAMPLES],
+    count: usize,
+    index: usize,
+    last: Option<f32>,
+}
+
+impl SensorFilter {
+    pub fn new() -> SensorFilter {
+        SensorFilter {
+            history: [0.0; MAX_FILTER_SAMPLES],
+            count: 0,
+            index: 0,
+            last: None,
+        }
+    }
+
+    /**
+     * @brief copia de las ultimas `samples` lecturas y cuantas hay
+     */
+    fn recent(&self, samples: u8) -> ([f32; MAX_FILTER_SAMPLES], usize) {
+        let n = (samples as usize).clamp(1, MAX_FILTER_SAMPLES).min(self.count);
+        let mut values = [0.0; MAX_FILTER_SAMPLES];
+
+        for (i, value) in values[..n].iter_mut().enumerate() {
+            *value = self.history[(self.index + MAX_FILTER_SAMPLES - 1 - i) % MAX_FILTER_SAMPLES];
+        }
+
+        (values, n)
+    }
+
+    /**
+     * @brief agrega la lectura y devuelve el valor filtrado
+     */
+    pub fn apply(&mut self, value: f32, cfg: &FilterConfig) -> f32 {
+        self.history[self.index] = value;
+        self.index = (self.index + 1) % MAX_FILTER_SAMPLES;
+        self.count = (self.count + 1).min(MAX_FILTER_SAMPLES);
+
+        let output = match *cfg {
+            FilterConfig::None => value,
+            FilterConfig::Ema { alpha } => match self.last {
+                Some(last) => alpha * value + (1.0 - alpha) * last,
+                None => value,
+            },
+            FilterConfig::MovingAverage { samples } => {
+                let (values, n) = self.recent(samples);
+                values[..n].iter().sum::<f32>() / n as f32
+            }
+            FilterConfig::Median { samples } => {
+                let (mut values, n) = self.recent(samples);
+                let values = &mut values[..n];
+                values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+
+                if n % 2 == 1 { values[n / 2] } else { (values[n / 2 - 1] + values[n / 2]) / 2.0 }
+            }
+            FilterConfig::RateLimit { max_step } => match self.last {
+                Some(last) => last + (value - last).clamp(-max_step, max_step),
+                None => value,
+            },
+        };
+
+        self.last = Some(output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(cfg: FilterConfig, values: &[f32]) -> f32 {
+        let mut filter = SensorFilter::new();
+        let mut output = 0.0;
+        for value in values {
+            output = filter.apply(*value, &cfg);
+        }
+        output
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= 0.001, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn none() {
+        assert_close(run(FilterConfig::None, &[100.0, 300.0]), 300.0);
+    }
+
+    #[test]
+    fn ema() {
+        let cfg = FilterConfig::Ema { alpha: 0.5 };
+        // la primera lectura pasa directo
+        assert_close(run(cfg, &[100.0]), 100.0);
+        assert_close(run(cfg, &[100.0, 200.0]), 150.0);
+        assert_close(run(cfg, &[100.0, 200.0, 200.0]), 175.0);
+
+        assert_close(run(FilterConfig::Ema { alpha: 1.0 }, &[100.0, 200.0]), 200.0);
+        assert_close(run(FilterConfig::Ema { alpha: 0.0 }, &[100.0, 200.0]), 100.0);
+    }
+
+    #[test]
+    fn moving_average() {
+        let cfg = FilterConfig::MovingAverage { samples: 4 };
+        // con menos lecturas que `samples` promedia las que hay
+        assert_close(run(cfg, &[100.0, 200.0]), 150.0);
+        assert_close(run(cfg, &[100.0, 200.0, 300.0, 400.0, 500.0]), 350.0);
+
+        // mas que el historial se limita a MAX_FILTER_SAMPLES, 0 a una lectura
+        let values = [0.0, 10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 10.0];
+        assert_close(run(FilterConfig::MovingAverage { samples: 20 }, &values), 10.0);
+        assert_close(run(FilterConfig::MovingAverage { samples: 0 }, &[100.0, 200.0]), 200.0);
+    }
+
+    #[test]
+    fn median_odd() {
+        let cfg = FilterConfig::Median { samples: 3 };
+        // saca el pico suelto
+        assert_close(run(cfg, &[100.0, 3000.0, 110.0]), 110.0);
+        assert_close(run(cfg, &[100.0, 3000.0, 110.0, 105.0]), 110.0);
+    }
+
+    #[test]
+    fn median_even() {
+        let cfg = FilterConfig::Median { samples: 4 };
+        // promedio de las dos del medio
+        assert_close(run(cfg, &[400.0, 100.0, 300.0, 200.0]), 250.0);
+        assert_close(run(cfg, &[100.0, 200.0]), 150.0);
+    }
+
+    #[test]
+    fn history_wraps() {
+        let cfg = FilterConfig::MovingAverage { samples: 2 };
+        let mut values = [0.0; MAX_FILTER_SAMPLES + 3];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = i as f32;
+        }
+
+        let last = values.len() as f32 - 1.0;
+        assert_close(run(cfg, &values), last - 0.5);
+    }
+
+    #[test]
+    fn rate_limit() {
+        let cfg = FilterConfig::RateLimit { max_step: 50.0 };
+        assert_close(run(cfg, &[100.0, 300.0]), 150.0);
+        assert_close(run(cfg, &[100.0, 300.0, 300.0]), 200.0);
+        assert_close(run(cfg, &[100.0, 0.0]), 50.0);
+        assert_close(run(cfg, &[100.0, 120.0]), 120.0);
+    }
+
+    #[test]
+    fn validate() {
+        let mut cfg = FilterConfig::Ema { alpha: 1.5 };
+        assert!(cfg.validate());
+        assert_eq!(cfg, FilterConfig::Ema { alpha: 1.0 });
+
+        let mut cfg = FilterConfig::Ema { alpha: -0.2 };
+        assert!(cfg.validate());
+        assert_eq!(cfg, FilterConfig::Ema { alpha: 0.0 });
+
+        let mut cfg = FilterConfig::Ema { alpha: f32::NAN };
+        assert!(cfg.validate());
+        assert_eq!(cfg, FilterConfig::None);
+
+        let mut cfg = FilterConfig::RateLimit { max_step: -20.0 };
+        assert!(cfg.validate());
+        assert_eq!(cfg, FilterConfig::RateLimit { max_step: 20.0 });
+
+        let mut cfg = FilterConfig::Ema { alpha: 0.45 };
+        assert!(!cfg.validate());
+        assert_eq!(cfg, FilterConfig::Ema { alpha: 0.45 });
+    }
+
+    #[test]
+    fn validate_all_sensors() {
+        let ema = FilterConfig::Ema { alpha: 0.45 };
+        let mut cfg = SensorFiltersConfig { map: ema, tps: ema, clt: ema, iat: ema, batt: ema, lambda: ema };
+        assert!(!cfg.validate());
+
+        cfg.lambda = FilterConfig::Ema { alpha: 2.0 };
+        assert!(cfg.validate());
+        assert_eq!(*cfg.get(SensorTypes::ExternalLambda), FilterConfig::Ema { alpha: 1.0 });
+    }
+}
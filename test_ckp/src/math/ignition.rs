@@ -0,0 +1,139 @@
+// con chispa perdida cada bobina enciende una vez por vuelta
+pub const WASTED_SPARK_CYCLE: f32 = 360.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CoilTiming {
+    // grados del ciclo (desde el PMS del cilindro 1) donde empieza la carga y donde salta la chispa
+    pub charge_angle: f32,
+    pub spark_angle: f32,
+}
+
+pub fn wrap_angle(angle: f32, cycle_angle: f32) -> f32 {
+    let mut result = angle % cycle_angle;
+    if result < 0.0 {
+        result += cycle_angle;
+    }
+    result
+}
+
+/**
+ * @brief grados que gira el cigüeñal en `time` uS, `revolution_time` en uS
+ */
+pub fn time_to_angle(time: u32, revolution_time: u32) -> f32 {
+    if revolution_time == 0 {
+        return 0.0;
+    }
+    time as f32 * 360.0 / revolution_time as f32
+}
+
+/**
+ * @brief uS que tarda el cigüeñal en girar `angle` grados, `revolution_time` en uS
+ */
+pub fn angle_to_time(angle: f32, revolution_time: u32) -> u32 {
+    (angle.max(0.0) * revolution_time as f32 / 360.0) as u32
+}
+
+/**
+ * @brief PMS de los cilindros de la bobina `coil` respecto al cilindro 1, en grados
+ */
+pub fn get_coil_offset(coil: usize, coils: usize) -> f32 {
+    if coils == 0 {
+        return 0.0;
+    }
+    coil as f32 * WASTED_SPARK_CYCLE / coils as f32
+}
+
+/**
+ * @brief chispa `advance` grados antes del PMS y carga `dwell` uS antes de la chispa;
+ * si la carga no entra en una vuelta se carga la vuelta entera
+ */
+pub fn get_coil_timing(offset: f32, advance: f32, dwell: u32, revolution_time: u32) -> CoilTiming {
+    let dwell_angle = time_to_angle(dwell, revolution_time).min(WASTED_SPARK_CYCLE);
+
+    let spark_angle = wrap_angle(offset - advance, WASTED_SPARK_CYCLE);
+    let charge_angle = wrap_angle(spark_angle - dwell_angle, WASTED_SPARK_CYCLE);
+
+    CoilTiming { charge_angle, spark_angle }
+}
+
+/**
+ * @brief grados que faltan para llegar a `angle` desde `crank_angle` (0..720)
+ */
+pub fn angle_until(angle: f32, crank_angle: f32) -> f32 {
+    wrap_angle(angle - crank_angle, WASTED_SPARK_CYCLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 6000 RPM
+    const REVOLUTION_TIME: u32 = 10_000;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= 0.01, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn wrap() {
+        assert_close(wrap_angle(370.0, 360.0), 10.0);
+        assert_close(wrap_angle(-10.0, 360.0), 350.0);
+        assert_close(wrap_angle(720.0, 360.0), 0.0);
+    }
+
+    #[test]
+    fn time_and_angle() {
+        assert_close(time_to_angle(2500, REVOLUTION_TIME), 90.0);
+        assert_eq!(angle_to_time(90.0, REVOLUTION_TIME), 2500);
+        assert_eq!(angle_to_time(-5.0, REVOLUTION_TIME), 0);
+        // sin RPM no hay angulo
+        assert_close(time_to_angle(2500, 0), 0.0);
+    }
+
+    #[test]
+    fn coil_offsets() {
+        // 4 cilindros: bobina 1 (1-4) en 0°, bobina 2 (2-3) en 180°
+        assert_close(get_coil_offset(0, 2), 0.0);
+        assert_close(get_coil_offset(1, 2), 180.0);
+        assert_close(get_coil_offset(0, 1), 0.0);
+        assert_close(get_coil_offset(1, 0), 0.0);
+    }
+
+    #[test]
+    fn spark_before_tdc() {
+        let timing = get_coil_timing(0.0, 15.0, 3000, REVOLUTION_TIME);
+        assert_close(timing.spark_angle, 345.0);
+        // 3000 uS a 6000 RPM = 108°
+        assert_close(timing.charge_angle, 237.0);
+
+        let timing = get_coil_timing(180.0, 15.0, 3000, REVOLUTION_TIME);
+        assert_close(timing.spark_angle, 165.0);
+        assert_close(timing.charge_angle, 57.0);
+    }
+
+    #[test]
+    fn retard_after_tdc() {
+        let timing = get_coil_timing(0.0, -5.0, 1000, REVOLUTION_TIME);
+        assert_close(timing.spark_angle, 5.0);
+        assert_close(timing.charge_angle, 329.0);
+    }
+
+    #[test]
+    fn dwell_follows_rpm() {
+        // 1000 RPM: 3000 uS = 18°
+        let timing = get_coil_timing(0.0, 10.0, 3000, 60_000);
+        assert_close(timing.charge_angle, 332.0);
+
+        // la carga no puede ser mas larga que la vuelta
+        let timing = get_coil_timing(0.0, 10.0, 20_000, REVOLUTION_TIME);
+        assert_close(timing.charge_angle, timing.spark_angle);
+    }
+
+    #[test]
+    fn angle_to_next_event() {
+        assert_close(angle_until(237.0, 200.0), 37.0);
+        // la bobina enciende en las dos vueltas del ciclo
+        assert_close(angle_until(237.0, 560.0), 37.0);
+        assert_close(angle_until(10.0, 350.0), 20.0);
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(not(test))]
+use micromath::F32Ext;
+
+/**
+ * @brief caudal del inyector en mg/uS, corregido por la presion de combustible,
+ * `flow_cc_min` medido a `ref_pressure`
+ */
+pub fn get_injector_flow(flow_cc_min: f32, fuel_density: f32, fuel_pressure: f32, ref_pressure: f32) -> f32 {
+    let mut pressure_correction = 1.0;
+
+    if ref_pressure > 0.0 && fuel_pressure > 0.0 {
+        pressure_correction = (fuel_pressure / ref_pressure).sqrt();
+    }
+
+    // cc/min * g/cc = g/min -> mg/uS
+    flow_cc_min * fuel_density * pressure_correction / 60_000.0
+}
+
+/**
+ * @brief tiempo de inyeccion (uS) por inyector por ciclo para entregar `fuel_mass` (mg) a cada cilindro,
+ * `flow` en mg/uS
+ */
+pub fn get_pulse_width(fuel_mass: f32, flow: f32, injector_count: u8, cylinder_count: u8) -> f32 {
+    if flow <= 0.0 || injector_count == 0 {
+        return 0.0;
+    }
+
+    // si hay menos inyectores que cilindros cada uno alimenta a mas de un cilindro
+    let fuel_per_injector = fuel_mass * cylinder_count as f32 / injector_count as f32;
+
+    fuel_per_injector / flow
+}
+
+/**
+ * @brief tiempo de inyeccion (uS) para `air_mass` (mg por cilindro) con la mezcla objetivo
+ */
+pub fn get_required_fuel(air_mass: f32, stoich: f32, lambda: f32, flow: f32, injector_count: u8, cylinder_count: u8) -> f32 {
+    let target_afr = stoich * lambda;
+
+    if target_afr <= 0.0 {
+        return 0.0;
+    }
+
+    get_pulse_width(air_mass / target_afr, flow, injector_count, cylinder_count)
+}
+
+/**
+ * @brief uS disponibles por inyeccion a estas RPM, segun cuantas inyecciones hay por ciclo
+ */
+pub fn get_available_time(rpm: i32, injections_per_cycle: u32) -> f32 {
+    if rpm <= 0 || injections_per_cycle == 0 {
+        return 0.0;
+    }
+
+    // 720° = dos vueltas
+    120_000_000.0 / rpm as f32 / injections_per_cycle as f32
+}
+
+pub fn get_duty_cycle(pulse_width: f32, available_time: f32) -> f32 {
+    if available_time <= 0.0 {
+        return 0.0;
+    }
+
+    pulse_width / available_time * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 110cc/min con nafta (0.726 g/cc) = 0.001331 mg/uS
+    const FLOW: f32 = 110.0 * 0.726 / 60_000.0;
+
+    fn assert_close(value: f32, expected: f32, tolerance: f32) {
+        assert!((value - expected).abs() <= tolerance, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn injector_flow() {
+        assert_close(get_injector_flow(110.0, 0.726, 300.0, 300.0), 0.001331, 0.0000001);
+        // sin presion de referencia no se corrige
+        assert_close(get_injector_flow(110.0, 0.726, 400.0, 0.0), 0.001331, 0.0000001);
+        // 0.001331 * sqrt(400 / 300)
+        assert_close(get_injector_flow(110.0, 0.726, 400.0, 300.0), 0.0015369, 0.0000001);
+    }
+
+    #[test]
+    fn pulse_width() {
+        // 20mg / 0.001331
+        assert_close(get_pulse_width(20.0, FLOW, 4, 4), 15026.3, 0.5);
+        // dos inyectores para cuatro cilindros, el doble de combustible cada uno
+        assert_close(get_pulse_width(20.0, FLOW, 2, 4), 30052.6, 1.0);
+        assert_eq!(get_pulse_width(20.0, FLOW, 0, 4), 0.0);
+        assert_eq!(get_pulse_width(20.0, 0.0, 4, 4), 0.0);
+    }
+
+    #[test]
+    fn required_fuel() {
+        // 480mg / 14.7 = 32.653mg -> / 0.001331
+        assert_close(get_required_fuel(480.0, 14.7, 1.0, FLOW, 4, 4), 24532.7, 1.0);
+        // lambda 0.85 -> 38.415mg
+        assert_close(get_required_fuel(480.0, 14.7, 0.85, FLOW, 4, 4), 28862.0, 1.0);
+    }
+
+    #[test]
+    fn required_fuel_invalid_afr() {
+        assert_eq!(get_required_fuel(480.0, 14.7, 0.0, FLOW, 4, 4), 0.0);
+        assert_eq!(get_required_fuel(480.0, -14.7, 1.0, FLOW, 4, 4), 0.0);
+    }
+
+    #[test]
+    fn available_time_and_duty() {
+        assert_close(get_available_time(6000, 2), 10_000.0, 0.01);
+        assert_close(get_available_time(3000, 1), 40_000.0, 0.01);
+        assert_eq!(get_available_time(0, 2), 0.0);
+
+        assert_close(get_duty_cycle(8000.0, 10_000.0), 80.0, 0.001);
+        assert_eq!(get_duty_cycle(8000.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::math::tables::{get_nearest_cell, DataT, TABLE_SCALE, TABLE_SIZE};
+
+/**
+ * @brief tabla de correcciones en cero con los ejes de la VE
+ */
+pub fn get_empty_ltft(ve: &DataT) -> DataT {
+    let mut ltft = [[0i32; TABLE_SIZE]; TABLE_SIZE];
+
+    ltft[0] = ve[0];
+    for (row, ve_row) in ltft.iter_mut().zip(ve.iter()).skip(1) {
+        row[0] = ve_row[0];
+    }
+
+    ltft
+}
+
+/**
+ * @brief pone en cero las celdas, los ejes quedan
+ */
+pub fn clear_ltft(ltft: &mut DataT) {
+    for row in ltft[1..].iter_mut() {
+        for cell in row[1..].iter_mut() {
+            *cell = 0;
+        }
+    }
+}
+
+/**
+ * @brief mueve la celda (escalada por TABLE_SCALE) `learn_rate` * `trim` (%) por segundo durante `elapsed` (S),
+ * sin pasar de +-`max_cell_trim` (%)
+ */
+pub fn learn_cell(cell: i32, trim: f32, learn_rate: f32, elapsed: f32, max_cell_trim: f32) -> i32 {
+    let limit = max_cell_trim * TABLE_SCALE;
+    let step = learn_rate * trim * elapsed * TABLE_SCALE;
+
+    (cell as f32 + step).clamp(-limit, limit) as i32
+}
+
+/**
+ * @brief aprende en la celda mas cercana a RPM / carga, devuelve true si cambio
+ */
+pub fn learn_ltft(ltft: &mut DataT, rpm: f32, load: f32, trim: f32, learn_rate: f32, elapsed: f32, max_cell_trim: f32) -> bool {
+    let (row, column) = get_nearest_cell(ltft, rpm, load);
+    let cell = learn_cell(ltft[row][column], trim, learn_rate, elapsed, max_cell_trim);
+
+    if cell == ltft[row][column] {
+        return false;
+    }
+
+    ltft[row][column] = cell;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::tables::get_table_value;
+
+    // X: 1000..16000 RPM, Y: 10..160
+    fn get_test_ve() -> DataT {
+        let mut ve = [[8000; TABLE_SIZE]; TABLE_SIZE];
+
+        ve[0][0] = 0;
+        for (i, cell) in ve[0].iter_mut().enumerate().skip(1) {
+            *cell = i as i32 * 1000;
+        }
+        for (i, row) in ve.iter_mut().enumerate().skip(1) {
+            row[0] = i as i32 * 10;
+        }
+
+        ve
+    }
+
+    #[test]
+    fn empty_ltft_keeps_axes() {
+        let ve = get_test_ve();
+        let ltft = get_empty_ltft(&ve);
+
+        assert_eq!(ltft[0], ve[0]);
+        for row in 1..TABLE_SIZE {
+            assert_eq!(ltft[row][0], ve[row][0]);
+            assert!(ltft[row][1..].iter().all(|cell| *cell == 0));
+        }
+    }
+
+    #[test]
+    fn clear_keeps_axes() {
+        let ve = get_test_ve();
+        let mut ltft = ve;
+
+        clear_ltft(&mut ltft);
+
+        assert_eq!(ltft, get_empty_ltft(&ve));
+    }
+
+    #[test]
+    fn cell_follows_trim() {
+        // 0.1 * 5% durante 2 S = +1%
+        assert_eq!(learn_cell(0, 5.0, 0.1, 2.0, 15.0), 100);
+        assert_eq!(learn_cell(100, -5.0, 0.1, 2.0, 15.0), 0);
+        assert_eq!(learn_cell(250, 0.0, 0.1, 2.0, 15.0), 250);
+    }
+
+    #[test]
+    fn cell_is_clamped() {
+        assert_eq!(learn_cell(1400, 20.0, 1.0, 1.0, 15.0), 1500);
+        assert_eq!(learn_cell(-1400, -20.0, 1.0, 1.0, 15.0), -1500);
+        // bajar el limite recorta lo que ya estaba aprendido
+        assert_eq!(learn_cell(1500, 0.0, 0.1, 1.0, 10.0), 1000);
+    }
+
+    #[test]
+    fn learns_nearest_cell() {
+        let mut ltft = get_empty_ltft(&get_test_ve());
+
+        assert!(learn_ltft(&mut ltft, 3000.0, 50.0, 10.0, 0.1, 1.0, 15.0));
+        assert_eq!(ltft[5][3], 100);
+        assert!((get_table_value(&ltft, 3000.0, 50.0) - 1.0).abs() <= 0.001);
+
+        // un paso menor a una unidad de la celda se pierde al redondear
+        assert!(!learn_ltft(&mut ltft, 3000.0, 50.0, 0.5, 0.01, 0.1, 15.0));
+        assert_eq!(ltft[5][3], 100);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod calibration;
+pub mod diagnostics;
+pub mod filters;
+pub mod ignition;
+pub mod injectors;
+pub mod ltft;
+pub mod sensor_faults;
+pub mod speed_density;
+pub mod tables;
+pub mod thermistor;
+pub mod tps_calibration;
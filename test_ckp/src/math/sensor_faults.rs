@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::diagnostics::{DiagnosticCode, Diagnostics};
+
+pub const SENSOR_COUNT: usize = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorTypes {
+    MAP,
+    TPS,
+    CooltanTemp,
+    AirTemp,
+    BatteryVoltage,
+    ExternalLambda,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct FaultConfig {
+    pub enabled: bool,
+    // ventana valida en mV en el pin
+    pub min_mv: f32,
+    pub max_mv: f32,
+    // mS fuera de la ventana antes de marcar la falla
+    pub persistence: u32,
+    // valor (en la unidad del sensor) mientras esta en falla
+    pub substitute: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct SensorFaultsConfig {
+    pub map: FaultConfig,
+    pub tps: FaultConfig,
+    pub clt: FaultConfig,
+    pub iat: FaultConfig,
+    pub batt: FaultConfig,
+    pub lambda: FaultConfig,
+}
+
+impl SensorFaultsConfig {
+    pub fn get(&self, sensor: SensorTypes) -> &FaultConfig {
+        match sensor {
+            SensorTypes::MAP => &self.map,
+            SensorTypes::TPS => &self.tps,
+            SensorTypes::CooltanTemp => &self.clt,
+            SensorTypes::AirTemp => &self.iat,
+            SensorTypes::BatteryVoltage => &self.batt,
+            SensorTypes::ExternalLambda => &self.lambda,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SensorFaults {
+    // cada bit es un SensorTypes en falla
+    pub active: u8,
+    // mS desde que cada sensor salio de la ventana
+    out_of_range_since: [Option<u32>; SENSOR_COUNT],
+}
+
+impl SensorFaults {
+    pub fn new() -> SensorFaults {
+        SensorFaults {
+            active: 0,
+            out_of_range_since: [None; SENSOR_COUNT],
+        }
+    }
+
+    pub fn is_active(&self, sensor: SensorTypes) -> bool {
+        self.active & (1 << sensor as u8) != 0
+    }
+
+    /**
+     * @brief revisa la lectura (mV) contra la ventana valida, true si el sensor esta en falla, `now` en mS
+     */
+    pub fn check(&mut self, sensor: SensorTypes, millivolts: f32, cfg: &FaultConfig, now: u32) -> bool {
+        let index = sensor as usize;
+        let out_of_range = millivolts < cfg.min_mv || millivolts > cfg.max_mv;
+
+        if !cfg.enabled || !out_of_range {
+            self.out_of_range_since[index] = None;
+            self.active &= !(1 << index);
+            return false;
+        }
+
+        let since = *self.out_of_range_since[index].get_or_insert(now);
+        if now.wrapping_sub(since) >= cfg.persistence {
+            self.active |= 1 << index;
+        }
+
+        self.is_active(sensor)
+    }
+
+    /**
+     * @brief pasa las fallas de los sensores a los diagnosticos
+     */
+    pub fn report(&self, diagnostics: &mut Diagnostics) {
+        diagnostics.set(DiagnosticCode::MapSensorFault, self.is_active(SensorTypes::MAP));
+        diagnostics.set(DiagnosticCode::TpsSensorFault, self.is_active(SensorTypes::TPS));
+        diagnostics.set(DiagnosticCode::CltSensorFault, self.is_active(SensorTypes::CooltanTemp));
+        diagnostics.set(DiagnosticCode::IatSensorFault, self.is_active(SensorTypes::AirTemp));
+        diagnostics.set(DiagnosticCode::BatterySensorFault, self.is_active(SensorTypes::BatteryVoltage));
+        diagnostics.set(DiagnosticCode::LambdaSensorFault, self.is_active(SensorTypes::ExternalLambda));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CFG: FaultConfig = FaultConfig { enabled: true, min_mv: 50.0, max_mv: 3250.0, persistence: 500, substitute: 101.3 };
+
+    #[test]
+    fn in_range() {
+        let mut faults = SensorFaults::new();
+
+        assert!(!faults.check(SensorTypes::MAP, 50.0, &CFG, 0));
+        assert!(!faults.check(SensorTypes::MAP, 3250.0, &CFG, 1000));
+        assert_eq!(faults.active, 0);
+    }
+
+    #[test]
+    fn persistence() {
+        let mut faults = SensorFaults::new();
+
+        // abierto: tiene que seguir fuera de la ventana `persistence` mS
+        assert!(!faults.check(SensorTypes::MAP, 10.0, &CFG, 1000));
+        assert!(!faults.check(SensorTypes::MAP, 10.0, &CFG, 1499));
+        assert!(faults.check(SensorTypes::MAP, 10.0, &CFG, 1500));
+        assert!(faults.is_active(SensorTypes::MAP));
+        assert!(!faults.is_active(SensorTypes::TPS));
+
+        // un pico que vuelve a la ventana reinicia la cuenta
+        assert!(!faults.check(SensorTypes::TPS, 3300.0, &CFG, 0));
+        assert!(!faults.check(SensorTypes::TPS, 1000.0, &CFG, 400));
+        assert!(!faults.check(SensorTypes::TPS, 3300.0, &CFG, 450));
+        assert!(!faults.check(SensorTypes::TPS, 3300.0, &CFG, 900));
+        assert!(faults.check(SensorTypes::TPS, 3300.0, &CFG, 950));
+    }
+
+    #[test]
+    fn persistence_across_wraparound() {
+        let mut faults = SensorFaults::new();
+        let start = u32::MAX - 200;
+
+        assert!(!faults.check(SensorTypes::CooltanTemp, 10.0, &CFG, start));
+        assert!(!faults.check(SensorTypes::CooltanTemp, 10.0, &CFG, 100));
+        assert!(faults.check(SensorTypes::CooltanTemp, 10.0, &CFG, 299));
+    }
+
+    #[test]
+    fn clears_back_in_range() {
+        let mut faults = SensorFaults::new();
+
+        faults.check(SensorTypes::AirTemp, 3300.0, &CFG, 0);
+        assert!(faults.check(SensorTypes::AirTemp, 3300.0, &CFG, 500));
+
+        assert!(!faults.check(SensorTypes::AirTemp, 1500.0, &CFG, 510));
+        assert!(!faults.is_active(SensorTypes::AirTemp));
+
+        // al volver a salir arranca de nuevo la persistencia
+        assert!(!faults.check(SensorTypes::AirTemp, 3300.0, &CFG, 600));
+        assert!(!faults.check(SensorTypes::AirTemp, 3300.0, &CFG, 1000));
+        assert!(faults.check(SensorTypes::AirTemp, 3300.0, &CFG, 1100));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut faults = SensorFaults::new();
+        let cfg = FaultConfig { enabled: false, ..CFG };
+
+        faults.check(SensorTypes::BatteryVoltage, 0.0, &CFG, 0);
+        assert!(faults.check(SensorTypes::BatteryVoltage, 0.0, &CFG, 500));
+
+        // desactivarla con la falla marcada la limpia
+        assert!(!faults.check(SensorTypes::BatteryVoltage, 0.0, &cfg, 600));
+        assert!(!faults.is_active(SensorTypes::BatteryVoltage));
+    }
+
+    #[test]
+    fn substitutes_per_sensor() {
+        let cfg = SensorFaultsConfig {
+            map: FaultConfig { substitute: 101.3, ..CFG },
+            tps: FaultConfig { substitute: 0.0, ..CFG },
+            clt: FaultConfig { substitute: 80.0, ..CFG },
+            iat: FaultConfig { substitute: 25.0, ..CFG },
+            batt: FaultConfig { substitute: 13.5, ..CFG },
+            lambda: FaultConfig { substitute: 1.0, ..CFG },
+        };
+
+        assert_eq!(cfg.get(SensorTypes::MAP).substitute, 101.3);
+        assert_eq!(cfg.get(SensorTypes::TPS).substitute, 0.0);
+        assert_eq!(cfg.get(SensorTypes::CooltanTemp).substitute, 80.0);
+        assert_eq!(cfg.get(SensorTypes::AirTemp).substitute, 25.0);
+        assert_eq!(cfg.get(SensorTypes::BatteryVoltage).substitute, 13.5);
+        assert_eq!(cfg.get(SensorTypes::ExternalLambda).substitute, 1.0);
+    }
+
+    #[test]
+    fn diagnostics_mapping() {
+        let sensors = [
+            (SensorTypes::MAP, DiagnosticCode::MapSensorFault),
+            (SensorTypes::TPS, DiagnosticCode::TpsSensorFault),
+            (SensorTypes::CooltanTemp, DiagnosticCode::CltSensorFault),
+            (SensorTypes::AirTemp, DiagnosticCode::IatSensorFault),
+            (SensorTypes::BatteryVoltage, DiagnosticCode::BatterySensorFault),
+            (SensorTypes::ExternalLambda, DiagnosticCode::LambdaSensorFault),
+        ];
+
+        for (sensor, code) in sensors {
+            let mut faults = SensorFaults::new();
+            let mut diagnostics = Diagnostics::new();
+            diagnostics.set(DiagnosticCode::InjectorDutyOverflow, true);
+
+            faults.check(sensor, 0.0, &CFG, 0);
+            faults.check(sensor, 0.0, &CFG, 500);
+            faults.report(&mut diagnostics);
+
+            assert!(diagnostics.is_active(code));
+            // solo la suya, lo que no es de sensores queda
+            assert_eq!(diagnostics.active, (1 << code as u8) | 1);
+
+            faults.check(sensor, 1000.0, &CFG, 600);
+            faults.report(&mut diagnostics);
+            assert_eq!(diagnostics.active, 1);
+        }
+    }
+}
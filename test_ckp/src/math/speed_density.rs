@@ -0,0 +1,50 @@
+// constante de los gases para aire seco, kPa * L / (g * K)
+pub const R_AIR: f32 = 0.287;
+pub const KELVIN: f32 = 273.15;
+
+/**
+ * @brief densidad del aire en g/L, a partir de la presion (kPa) y temperatura (°C)
+ */
+pub fn get_air_density(pressure: f32, temperature: f32) -> f32 {
+    pressure / (R_AIR * (temperature + KELVIN))
+}
+
+/**
+ * @brief masa de aire por cilindro por ciclo (mg), VE en %
+ */
+pub fn get_air_mass(displacement: u32, cylinder_count: u8, ve: f32, map: f32, iat: f32) -> f32 {
+    // cc -> L
+    let cylinder_volume = displacement as f32 / cylinder_count.max(1) as f32 / 1000.0;
+
+    cylinder_volume * (ve / 100.0) * get_air_density(map, iat) * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32, tolerance: f32) {
+        assert!((value - expected).abs() <= tolerance, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn air_density_at_standard_conditions() {
+        // 101.325 / (0.287 * 293.15)
+        assert_close(get_air_density(101.325, 20.0), 1.204336, 0.00001);
+        // 50 / (0.287 * 273.15)
+        assert_close(get_air_density(50.0, 0.0), 0.637801, 0.00001);
+    }
+
+    #[test]
+    fn air_mass_per_cylinder() {
+        // 1600cc / 4 = 0.4L * 1.204336 g/L
+        assert_close(get_air_mass(1600, 4, 100.0, 101.325, 20.0), 481.734, 0.01);
+        assert_close(get_air_mass(1600, 4, 50.0, 101.325, 20.0), 240.867, 0.01);
+    }
+
+    #[test]
+    fn air_mass_without_cylinders() {
+        // se toma un cilindro para no dividir por cero
+        assert_close(get_air_mass(400, 0, 100.0, 101.325, 20.0), 481.734, 0.01);
+    }
+}
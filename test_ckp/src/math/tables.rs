@@ -0,0 +1,168 @@
+// fila 0: eje X (RPM), columna 0: eje Y (carga), el resto son los valores x100
+pub type DataT = [[i32; 17]; 17];
+// pares [x, y], con x creciente e y x100
+pub type PlotData = [[i32; 2]; 10];
+
+pub const TABLE_SIZE: usize = 17;
+pub const TABLE_SCALE: f32 = 100.0;
+
+// devuelve el indice bajo del tramo del eje que contiene a `value` y la fraccion dentro del tramo
+fn axis_position(axis: impl Fn(usize) -> i32, value: f32) -> (usize, f32) {
+    if value <= axis(1) as f32 {
+        return (1, 0.0);
+    }
+
+    for i in 2..TABLE_SIZE {
+        let low = axis(i - 1) as f32;
+        let high = axis(i) as f32;
+
+        if value <= high {
+            if high <= low {
+                return (i - 1, 0.0);
+            }
+            return (i - 1, (value - low) / (high - low));
+        }
+    }
+
+    (TABLE_SIZE - 2, 1.0)
+}
+
+/**
+ * @brief interpolacion bilineal sobre la tabla, fuera de los ejes se toma el borde
+ */
+pub fn get_table_value(table: &DataT, x: f32, y: f32) -> f32 {
+    let (xi, xf) = axis_position(|i| table[0][i], x);
+    let (yi, yf) = axis_position(|i| table[i][0], y);
+
+    let low = table[yi][xi] as f32 + (table[yi][xi + 1] - table[yi][xi]) as f32 * xf;
+    let high = table[yi + 1][xi] as f32 + (table[yi + 1][xi + 1] - table[yi + 1][xi]) as f32 * xf;
+
+    (low + (high - low) * yf) / TABLE_SCALE
+}
+
+/**
+ * @brief celda (fila, columna) mas cercana a (x, y)
+ */
+pub fn get_nearest_cell(table: &DataT, x: f32, y: f32) -> (usize, usize) {
+    let (xi, xf) = axis_position(|i| table[0][i], x);
+    let (yi, yf) = axis_position(|i| table[i][0], y);
+
+    let column = if xf >= 0.5 { xi + 1 } else { xi };
+    let row = if yf >= 0.5 { yi + 1 } else { yi };
+
+    (row, column)
+}
+
+/**
+ * @brief interpolacion lineal sobre la curva, fuera del eje se toma el borde
+ */
+pub fn get_plot_value(plot: &PlotData, x: f32) -> f32 {
+    if x <= plot[0][0] as f32 {
+        return plot[0][1] as f32 / TABLE_SCALE;
+    }
+
+    for i in 1..plot.len() {
+        let [x0, y0] = plot[i - 1];
+        let [x1, y1] = plot[i];
+
+        if x <= x1 as f32 {
+            if x1 <= x0 {
+                return y1 as f32 / TABLE_SCALE;
+            }
+            let y = y0 as f32 + (y1 - y0) as f32 * (x - x0 as f32) / (x1 - x0) as f32;
+            return y / TABLE_SCALE;
+        }
+    }
+
+    plot[plot.len() - 1][1] as f32 / TABLE_SCALE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // X: 1000..16000 RPM, Y: 10..160, valor de la celda = fila * 100 + columna
+    fn get_test_table() -> DataT {
+        let mut table = [[0; TABLE_SIZE]; TABLE_SIZE];
+
+        for (row, cells) in table.iter_mut().enumerate() {
+            for (column, cell) in cells.iter_mut().enumerate() {
+                *cell = match (row, column) {
+                    (0, 0) => 0,
+                    (0, _) => column as i32 * 1000,
+                    (_, 0) => row as i32 * 10,
+                    _ => (row * 100 + column) as i32 * TABLE_SCALE as i32,
+                };
+            }
+        }
+
+        table
+    }
+
+    // X: 0..9000, Y: 0..45
+    fn get_test_plot() -> PlotData {
+        let mut plot = [[0; 2]; 10];
+        for (i, point) in plot.iter_mut().enumerate() {
+            *point = [i as i32 * 1000, i as i32 * 500];
+        }
+        plot
+    }
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= 0.001, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn table_value_on_cells() {
+        let table = get_test_table();
+
+        assert_close(get_table_value(&table, 1000.0, 10.0), 101.0);
+        assert_close(get_table_value(&table, 5000.0, 80.0), 805.0);
+        assert_close(get_table_value(&table, 16000.0, 160.0), 1616.0);
+    }
+
+    #[test]
+    fn table_value_interpolated() {
+        let table = get_test_table();
+
+        // a mitad de camino en los dos ejes: fila 1.5, columna 1.5
+        assert_close(get_table_value(&table, 1500.0, 15.0), 151.5);
+        // fila 4.75, columna 3.25
+        assert_close(get_table_value(&table, 3250.0, 47.5), 478.25);
+    }
+
+    #[test]
+    fn table_value_outside_axes() {
+        let table = get_test_table();
+
+        assert_close(get_table_value(&table, 20000.0, 0.0), 116.0);
+        assert_close(get_table_value(&table, 0.0, 500.0), 1601.0);
+    }
+
+    #[test]
+    fn nearest_cell() {
+        let table = get_test_table();
+
+        assert_eq!(get_nearest_cell(&table, 3600.0, 42.0), (4, 4));
+        assert_eq!(get_nearest_cell(&table, 3400.0, 45.0), (5, 3));
+        assert_eq!(get_nearest_cell(&table, 0.0, 0.0), (1, 1));
+        assert_eq!(get_nearest_cell(&table, 99999.0, 999.0), (16, 16));
+    }
+
+    #[test]
+    fn plot_value() {
+        let plot = get_test_plot();
+
+        assert_close(get_plot_value(&plot, 1500.0), 7.5);
+        assert_close(get_plot_value(&plot, 4000.0), 20.0);
+        assert_close(get_plot_value(&plot, 8250.0), 41.25);
+    }
+
+    #[test]
+    fn plot_value_outside_axis() {
+        let plot = get_test_plot();
+
+        assert_close(get_plot_value(&plot, -5.0), 0.0);
+        assert_close(get_plot_value(&plot, 20000.0), 45.0);
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(not(test))]
+use micromath::F32Ext;
+
+use crate::math::speed_density::KELVIN;
+
+// el divisor se alimenta con la referencia del ADC
+pub const THERMISTOR_SUPPLY_MV: f32 = 3300.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SteinhartHart {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+}
+
+/**
+ * @brief coeficientes de Steinhart-Hart a partir de tres puntos [°C, ohm]
+ */
+pub fn get_coefficients(points: &[[f32; 2]; 3]) -> SteinhartHart {
+    let [[t1, r1], [t2, r2], [t3, r3]] = *points;
+
+    let (l1, l2, l3) = (r1.ln(), r2.ln(), r3.ln());
+    let (y1, y2, y3) = (1.0 / (t1 + KELVIN), 1.0 / (t2 + KELVIN), 1.0 / (t3 + KELVIN));
+
+    let g2 = (y2 - y1) / (l2 - l1);
+    let g3 = (y3 - y1) / (l3 - l1);
+
+    let c = (g3 - g2) / (l3 - l2) / (l1 + l2 + l3);
+    let b = g2 - c * (l1 * l1 + l1 * l2 + l2 * l2);
+    let a = y1 - (b + l1 * l1 * c) * l1;
+
+    SteinhartHart { a, b, c }
+}
+
+/**
+ * @brief resistencia del termistor (ohm) con el pull-up `bias_resistor`, None si esta abierto o en corto
+ */
+pub fn get_resistance(millivolts: f32, bias_resistor: f32) -> Option<f32> {
+    if millivolts <= 0.0 || millivolts >= THERMISTOR_SUPPLY_MV {
+        return None;
+    }
+
+    Some(bias_resistor * millivolts / (THERMISTOR_SUPPLY_MV - millivolts))
+}
+
+impl SteinhartHart {
+    /**
+     * @brief temperatura (°C) para la resistencia (ohm), None si queda fuera de la curva
+     */
+    pub fn temperature(&self, resistance: f32) -> Option<f32> {
+        if resistance <= 0.0 {
+            return None;
+        }
+
+        let ln_r = resistance.ln();
+        let inv_t = self.a + self.b * ln_r + self.c * ln_r * ln_r * ln_r;
+        if inv_t <= 0.0 {
+            return None;
+        }
+
+        Some(1.0 / inv_t - KELVIN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sensor de temperatura GM
+    const GM: [[f32; 2]; 3] = [[-40.0, 100_700.0], [30.0, 2_238.0], [100.0, 177.0]];
+
+    fn assert_close(value: f32, expected: f32, tolerance: f32) {
+        assert!((value - expected).abs() <= tolerance, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn coefficients_fit_points() {
+        let coefficients = get_coefficients(&GM);
+
+        for [temperature, resistance] in GM {
+            assert_close(coefficients.temperature(resistance).unwrap(), temperature, 0.1);
+        }
+    }
+
+    #[test]
+    fn temperature_between_points() {
+        let coefficients = get_coefficients(&GM);
+
+        // tabla del fabricante: 20°C = 3520 ohm, 80°C = 332 ohm
+        assert_close(coefficients.temperature(3_520.0).unwrap(), 20.0, 1.0);
+        assert_close(coefficients.temperature(332.0).unwrap(), 80.0, 1.0);
+        assert_eq!(coefficients.temperature(0.0), None);
+    }
+
+    #[test]
+    fn resistance() {
+        // mitad de la alimentacion = misma resistencia que el pull-up
+        assert_close(get_resistance(1650.0, 2490.0).unwrap(), 2490.0, 0.01);
+        assert_close(get_resistance(825.0, 2490.0).unwrap(), 830.0, 0.01);
+        // abierto / en corto
+        assert_eq!(get_resistance(0.0, 2490.0), None);
+        assert_eq!(get_resistance(THERMISTOR_SUPPLY_MV, 2490.0), None);
+    }
+}
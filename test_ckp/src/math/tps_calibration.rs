@@ -0,0 +1,171 @@
+use serde::Serialize;
+
+use crate::math::calibration::{linear_between, Calibration, ADC_FULL_SCALE_MV};
+
+// fuera de esto el sensor esta abierto o en corto
+const MIN_VALID_MV: f32 = 100.0;
+const MAX_VALID_MV: f32 = ADC_FULL_SCALE_MV - 100.0;
+
+// recorrido minimo entre cerrado y WOT
+const MIN_SPAN_MV: f32 = 500.0;
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum TpsCalibrationStep {
+    // mariposa cerrada
+    Closed,
+    // acelerador a fondo
+    WideOpen,
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum TpsCalibrationState {
+    Idle,
+    ClosedRecorded,
+    Done,
+    // se pidio WOT sin haber grabado cerrado
+    MissingClosed,
+    OutOfRange,
+    SpanTooSmall,
+}
+
+#[derive(Serialize, Debug, Copy, Clone)]
+pub struct TpsCalibration {
+    pub state: TpsCalibrationState,
+    pub closed_mv: f32,
+    pub wide_open_mv: f32,
+    // WOT por debajo de cerrado: sensor invertido o cableado al reves, la calibracion igual lo corrige
+    pub swapped: bool,
+}
+
+impl Default for TpsCalibration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TpsCalibration {
+    pub fn new() -> TpsCalibration {
+        TpsCalibration {
+            state: TpsCalibrationState::Idle,
+            closed_mv: 0.0,
+            wide_open_mv: 0.0,
+            swapped: false,
+        }
+    }
+
+    /**
+     * @brief graba un extremo (mV promediados), al completar los dos devuelve la nueva calibracion
+     */
+    pub fn record(&mut self, step: TpsCalibrationStep, millivolts: f32) -> Option<Calibration> {
+        if !(MIN_VALID_MV..=MAX_VALID_MV).contains(&millivolts) {
+            self.state = TpsCalibrationState::OutOfRange;
+            return None;
+        }
+
+        match step {
+            TpsCalibrationStep::Closed => {
+                self.closed_mv = millivolts;
+                self.state = TpsCalibrationState::ClosedRecorded;
+                None
+            }
+            TpsCalibrationStep::WideOpen => {
+                if self.state != TpsCalibrationState::ClosedRecorded {
+                    self.state = TpsCalibrationState::MissingClosed;
+                    return None;
+                }
+
+                self.wide_open_mv = millivolts;
+
+                let span = self.wide_open_mv - self.closed_mv;
+                if (-MIN_SPAN_MV..MIN_SPAN_MV).contains(&span) {
+                    self.state = TpsCalibrationState::SpanTooSmall;
+                    return None;
+                }
+
+                self.swapped = span < 0.0;
+                self.state = TpsCalibrationState::Done;
+
+                Some(linear_between(self.closed_mv, 0.0, self.wide_open_mv, 100.0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::calibration::apply_calibration;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() <= 0.001, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn closed_then_wide_open() {
+        let mut cal = TpsCalibration::new();
+
+        assert!(cal.record(TpsCalibrationStep::Closed, 500.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::ClosedRecorded);
+
+        let calibration = cal.record(TpsCalibrationStep::WideOpen, 2900.0).unwrap();
+        assert_eq!(cal.state, TpsCalibrationState::Done);
+        assert!(!cal.swapped);
+        assert_close(apply_calibration(500.0, &calibration), 0.0);
+        assert_close(apply_calibration(1700.0, &calibration), 50.0);
+        assert_close(apply_calibration(2900.0, &calibration), 100.0);
+    }
+
+    #[test]
+    fn swapped_sensor() {
+        let mut cal = TpsCalibration::new();
+
+        cal.record(TpsCalibrationStep::Closed, 2900.0);
+        let calibration = cal.record(TpsCalibrationStep::WideOpen, 500.0).unwrap();
+
+        assert_eq!(cal.state, TpsCalibrationState::Done);
+        assert!(cal.swapped);
+        // la recta queda invertida y sigue dando 0 cerrado / 100 a fondo
+        assert_close(apply_calibration(2900.0, &calibration), 0.0);
+        assert_close(apply_calibration(500.0, &calibration), 100.0);
+    }
+
+    #[test]
+    fn span_too_small() {
+        let mut cal = TpsCalibration::new();
+
+        cal.record(TpsCalibrationStep::Closed, 1000.0);
+        assert!(cal.record(TpsCalibrationStep::WideOpen, 1400.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::SpanTooSmall);
+
+        // invertido tambien se valida el recorrido
+        cal.record(TpsCalibrationStep::Closed, 1000.0);
+        assert!(cal.record(TpsCalibrationStep::WideOpen, 600.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::SpanTooSmall);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut cal = TpsCalibration::new();
+
+        assert!(cal.record(TpsCalibrationStep::Closed, 50.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::OutOfRange);
+
+        cal.record(TpsCalibrationStep::Closed, 500.0);
+        assert!(cal.record(TpsCalibrationStep::WideOpen, 3250.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::OutOfRange);
+    }
+
+    #[test]
+    fn wide_open_needs_closed() {
+        let mut cal = TpsCalibration::new();
+
+        assert!(cal.record(TpsCalibrationStep::WideOpen, 2900.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::MissingClosed);
+
+        // despues de terminar hay que volver a grabar cerrado
+        cal.record(TpsCalibrationStep::Closed, 500.0);
+        cal.record(TpsCalibrationStep::WideOpen, 2900.0).unwrap();
+        assert!(cal.record(TpsCalibrationStep::WideOpen, 2900.0).is_none());
+        assert_eq!(cal.state, TpsCalibrationState::MissingClosed);
+    }
+}